@@ -0,0 +1,169 @@
+//! Borrowers of lent copies and their contact details.
+
+use std::{fs, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::error::{AppError, Result};
+
+/// Someone copies can be lent to.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Borrower {
+    pub id: i64,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+}
+
+/// People copies get lent to, shared by all collections.
+pub struct Borrowers {
+    file_path: String,
+    pub list: Vec<Borrower>,
+}
+
+impl Borrowers {
+    /// Loads the borrowers from `file_path`, starting empty when the file doesn't exist yet.
+    pub fn new(file_path: &str) -> Result<Self> {
+        let list = match fs::read_to_string(file_path) {
+            Ok(data) => serde_json::from_str(&data).map_err(|e| {
+                AppError::Parse(format!("{} is not a borrower list: {}", file_path, e))
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => Err(AppError::file(file_path, e))?,
+        };
+        Ok(Borrowers {
+            file_path: file_path.to_string(),
+            list,
+        })
+    }
+
+    /// Reads the file again, e.g. after another process registered borrowers.
+    pub fn reload(&mut self) -> Result<()> {
+        *self = Borrowers::new(&self.file_path)?;
+        Ok(())
+    }
+
+    /// The borrower with the given id.
+    pub fn get(&self, id: i64) -> Option<&Borrower> {
+        self.list.iter().find(|b| b.id == id)
+    }
+
+    /// Name to show for a borrower id, also for loans without a known borrower.
+    pub fn name(&self, id: Option<i64>) -> String {
+        id.and_then(|id| self.get(id))
+            .map_or("Unknown borrower".to_string(), |b| b.name.clone())
+    }
+
+    /// Looks a borrower up by name, registering a new one if nobody matches. New
+    /// borrowers are only kept in memory, see [`Borrowers::registering`].
+    pub fn find_or_add(&mut self, name: &str) -> Result<i64> {
+        let name = name.trim();
+        if name.is_empty() {
+            Err(AppError::Validation(
+                "Borrower name can't be empty".to_string(),
+            ))?
+        }
+        if let Some(borrower) = self.list.iter().find(|b| b.name.eq_ignore_ascii_case(name)) {
+            return Ok(borrower.id);
+        }
+        let id = self.list.iter().map(|b| b.id).max().unwrap_or(0) + 1;
+        self.list.push(Borrower {
+            id,
+            name: name.to_string(),
+            contact: None,
+        });
+        Ok(id)
+    }
+
+    /// Runs `change`, which may register borrowers, e.g. a loan being saved. They are
+    /// written once it succeeds and forgotten when it fails, so no borrower is left
+    /// behind without a loan.
+    pub fn registering<T>(
+        &mut self,
+        change: impl FnOnce(&mut Borrowers) -> Result<T>,
+    ) -> Result<T> {
+        let known = self.list.len();
+        match change(self) {
+            Ok(value) => {
+                if self.list.len() != known {
+                    self.save()?;
+                }
+                Ok(value)
+            }
+            Err(e) => {
+                self.list.truncate(known);
+                Err(e)
+            }
+        }
+    }
+
+    /// Sets or, with an empty text, clears the contact details of the borrower called `name`.
+    pub fn set_contact(&mut self, name: &str, contact: &str) -> Result<&Borrower> {
+        let index = self
+            .list
+            .iter()
+            .position(|b| b.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| AppError::NotFound(format!("No borrower called \"{}\"", name)))?;
+        self.list[index].contact = Some(contact.trim().to_string()).filter(|c| !c.is_empty());
+        self.save()?;
+        Ok(&self.list[index])
+    }
+
+    fn save(&self) -> Result<()> {
+        fs::write(&self.file_path, serde_json::to_string(&self.list)?)
+            .map_err(|e| AppError::file(&self.file_path, e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "borrowers-test-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn only_a_missing_file_starts_empty() {
+        let path = temp_path("corrupt");
+        assert!(Borrowers::new(&path).unwrap().list.is_empty());
+        fs::write(&path, "[{\"id\": 1,").unwrap();
+        assert!(matches!(Borrowers::new(&path), Err(AppError::Parse(_))));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn borrowers_are_only_saved_with_their_loan() {
+        let path = temp_path("registering");
+        let mut borrowers = Borrowers::new(&path).unwrap();
+
+        let failed: Result<()> = borrowers.registering(|borrowers| {
+            borrowers.find_or_add("Alice")?;
+            Err(AppError::Storage("Disk full".to_string()))
+        });
+        assert!(failed.is_err());
+        assert!(borrowers.list.is_empty());
+        assert!(!std::path::Path::new(&path).exists());
+
+        let id = borrowers
+            .registering(|borrowers| borrowers.find_or_add(" alice "))
+            .unwrap();
+        assert_eq!(borrowers.find_or_add("ALICE").unwrap(), id);
+        borrowers
+            .set_contact("Alice", " alice@example.com ")
+            .unwrap();
+        assert!(borrowers.set_contact("Bob", "bob@example.com").is_err());
+
+        let saved = Borrowers::new(&path).unwrap();
+        assert_eq!(saved.list.len(), 1);
+        assert_eq!(saved.name(Some(id)), "alice");
+        assert_eq!(saved.list[0].contact.as_deref(), Some("alice@example.com"));
+        fs::remove_file(&path).unwrap();
+    }
+}
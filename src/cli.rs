@@ -0,0 +1,566 @@
+use std::{fmt, fs, path::Path};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use record_catalog::{
+    borrowers::Borrowers,
+    covers::CoverStore,
+    database::Database,
+    discogs::DiscogsClient,
+    export::{ExportFormat, ExportTable, DEFAULT_COLUMNS},
+    labels::{parse_label_code, sleeve_labels, to_pdf, to_svg, LabelLayout},
+    merge,
+    models::{
+        error::AppError,
+        item_holder::{grade_code, Item, ItemHolder, Loan},
+        settings::Settings,
+    },
+    search::SearchQuery,
+    site::generate_site,
+    stats::collection_stats,
+};
+
+use crate::server;
+
+pub const USAGE: &str = "Usage: hello-rust [<command> [args] [--json] [--collection <name>]]
+
+Without a command the terminal UI is started.
+
+Commands:
+  search <query>                      List the records matching a local search query
+  add <release-id>                    Add a copy of a Discogs release
+  show <release-id>                   Show a record and its copies
+  remove <release-id> [<item-id>]     Remove a copy, or a record without copies
+  lend <release-id> <item-id> <borrower> [--due YYYY-MM-DD] [--message <text>]
+  return <release-id> <item-id>       Mark a lent copy as returned
+  contact <borrower> [<details>]      Set or, without details, clear how to reach a borrower
+  export [<query>] [-o <file>] [--format json|csv|markdown|html] [--columns <a,b,..>] [--all]
+                                      Write the catalog or the copies matching a query
+  labels [<query>] -o <file.pdf|file.svg> [--layout L7160] [--skip <n>] [--all]
+                                      Print sleeve labels with a QR code per copy
+  lookup <code>                       Show the copy of a scanned label
+  import <file> [--dry-run]           Merge a catalog file into the collection
+  stats                               Totals and breakdowns of the collection
+  site [<query>] -o <dir> [--title <title>]
+                                      Generate a static web catalog of the records
+  serve [--bind 127.0.0.1:8080]       Serve the collection over a local HTTP JSON API
+  merge <left> <right> (-o <output> | --dry-run)
+
+Exit codes: 0 success, 1 failure, 2 invalid usage, 3 nothing found.";
+
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+
+type Result<T> = std::result::Result<T, CliError>;
+
+/// A command line that can't be run, or the error of the command that ran.
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Failed(AppError),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}\n\n{}", message, USAGE),
+            CliError::Failed(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl<E: Into<AppError>> From<E> for CliError {
+    fn from(error: E) -> Self {
+        CliError::Failed(error.into())
+    }
+}
+
+fn usage(message: &str) -> CliError {
+    CliError::Usage(message.to_string())
+}
+
+/// Runs a subcommand and returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    match run_command(args) {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit_code(&e)
+        }
+    }
+}
+
+fn exit_code(error: &CliError) -> i32 {
+    match error {
+        CliError::Usage(_) => EXIT_USAGE,
+        CliError::Failed(AppError::NotFound(_)) => EXIT_NOT_FOUND,
+        CliError::Failed(_) => EXIT_FAILURE,
+    }
+}
+
+/// Flags shared by all commands, everything else is positional.
+#[derive(Default)]
+struct Options {
+    json: bool,
+    dry_run: bool,
+    all: bool,
+    collection: Option<String>,
+    output: Option<String>,
+    format: Option<String>,
+    columns: Option<String>,
+    layout: Option<String>,
+    skip: Option<String>,
+    bind: Option<String>,
+    title: Option<String>,
+    due: Option<String>,
+    message: Option<String>,
+    args: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options> {
+        let mut options = Options::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| usage(&format!("{} needs a value", arg)))
+            };
+            match arg.as_str() {
+                "--json" => options.json = true,
+                "--dry-run" => options.dry_run = true,
+                "--all" => options.all = true,
+                "--collection" => options.collection = Some(value()?),
+                "-o" | "--output" => options.output = Some(value()?),
+                "--format" => options.format = Some(value()?),
+                "--columns" => options.columns = Some(value()?),
+                "--layout" => options.layout = Some(value()?),
+                "--skip" => options.skip = Some(value()?),
+                "--bind" => options.bind = Some(value()?),
+                "--title" => options.title = Some(value()?),
+                "--due" => options.due = Some(value()?),
+                "--message" => options.message = Some(value()?),
+                flag if flag.starts_with("--") => Err(usage(&format!("Unknown flag {}", flag)))?,
+                _ => options.args.push(arg.clone()),
+            }
+        }
+        Ok(options)
+    }
+
+    fn arg(&self, index: usize, name: &str) -> Result<&str> {
+        self.args
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| usage(&format!("Missing <{}>", name)))
+    }
+
+    fn id(&self, index: usize, name: &str) -> Result<i64> {
+        let arg = self.arg(index, name)?;
+        arg.parse()
+            .map_err(|_| usage(&format!("<{}> must be a number, got \"{}\"", name, arg)))
+    }
+
+    fn print<T: Serialize>(&self, value: &T, human: impl FnOnce()) -> Result<()> {
+        match self.json {
+            true => println!("{}", serde_json::to_string_pretty(value)?),
+            false => human(),
+        }
+        Ok(())
+    }
+}
+
+fn run_command(args: &[String]) -> Result<()> {
+    let (command, args) = args.split_first().ok_or_else(|| usage("Missing command"))?;
+    let options = Options::parse(args)?;
+    match command.as_str() {
+        "merge" => return run_merge(&options),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let settings = Settings::load()?;
+    let mut database = open_database(&settings, &options)?;
+    match command.as_str() {
+        "search" => search(&settings, &database, &options),
+        "add" => add(&settings, &mut database, &options),
+        "show" => show(&database, &options),
+        "remove" => remove(&mut database, &options),
+        "lend" => lend(&settings, &mut database, &options),
+        "return" => return_item(&settings, &mut database, &options),
+        "contact" => contact(&settings, &options),
+        "export" => export(&settings, &database, &options),
+        "import" => import(&mut database, &options),
+        "labels" => labels(&settings, &database, &options),
+        "lookup" => lookup(&database, &options),
+        "stats" => stats(&database, &options),
+        "site" => site(&settings, &database, &options),
+        "serve" => Ok(server::serve(
+            &settings,
+            database,
+            options.bind.as_deref().unwrap_or("127.0.0.1:8080"),
+        )?),
+        command => Err(usage(&format!("Unknown command \"{}\"", command)))?,
+    }
+}
+
+fn open_database(settings: &Settings, options: &Options) -> Result<Database> {
+    let collections = settings.collections();
+    let collection = match &options.collection {
+        Some(name) => collections
+            .iter()
+            .find(|c| c.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| AppError::NotFound(format!("No collection named \"{}\"", name)))?,
+        None => collections.first().ok_or_else(|| {
+            AppError::Configuration("No collection configured in settings.json".to_string())
+        })?,
+    };
+    Ok(Database::new(&collection.database_path)?)
+}
+
+fn holder_line(holder: &ItemHolder) -> String {
+    let artists: Vec<_> = holder
+        .record
+        .artists
+        .iter()
+        .map(|a| a.name.as_str())
+        .collect();
+    format!(
+        "{}\t{} - {}\t{} copies",
+        holder.record.id,
+        artists.join(", "),
+        holder.record.title,
+        holder.items.iter().filter(|i| i.is_in_collection()).count()
+    )
+}
+
+fn item_line(item: &Item) -> String {
+    format!(
+        "  #{}  {}  {}/{}  {}",
+        item.id,
+        item.state(),
+        grade_code(item.media_condition),
+        grade_code(item.sleeve_condition),
+        item.location
+            .as_ref()
+            .map_or("".to_string(), |l| l.to_string())
+    )
+}
+
+fn search(settings: &Settings, database: &Database, options: &Options) -> Result<()> {
+    let query = SearchQuery::parse(&options.args.join(" "), &settings.custom_fields);
+    let results = database.search(&query, None);
+    if results.is_empty() {
+        Err(AppError::NotFound("No records found".to_string()))?
+    }
+    options.print(&results, || {
+        results.iter().for_each(|h| println!("{}", holder_line(h)))
+    })
+}
+
+fn add(settings: &Settings, database: &mut Database, options: &Options) -> Result<()> {
+    let id = options.id(0, "release-id")?;
+    let client = DiscogsClient::new(&settings.discogs_key, settings.currency.clone());
+    let holder = database.add(client.get_release(id)?)?;
+    options.print(&holder, || {
+        println!(
+            "Added copy #{} of {}",
+            holder.items.last().map_or(0, |i| i.id),
+            holder.record.title
+        )
+    })
+}
+
+fn show(database: &Database, options: &Options) -> Result<()> {
+    let holder = database.holder(options.id(0, "release-id")?)?;
+    options.print(holder, || {
+        holder
+            .record
+            .get_lines()
+            .iter()
+            .for_each(|l| println!("{}", l));
+        println!("Copies:");
+        holder
+            .items
+            .iter()
+            .for_each(|item| println!("{}", item_line(item)));
+    })
+}
+
+fn remove(database: &mut Database, options: &Options) -> Result<()> {
+    let holder = database.holder(options.id(0, "release-id")?)?.clone();
+    match options.args.get(1) {
+        Some(_) => {
+            let item_id = options.id(1, "item-id")?;
+            let index = holder
+                .items
+                .iter()
+                .position(|i| i.id == item_id)
+                .ok_or_else(|| AppError::NotFound(format!("No copy #{}", item_id)))?;
+            database.remove_holder_item(&holder.record, index)?;
+            options.print(&holder.items[index], || {
+                println!("Removed copy #{} of {}", item_id, holder.record.title)
+            })
+        }
+        None if holder.items.is_empty() => {
+            database.remove_holder(&holder.record)?;
+            options.print(&holder, || println!("Removed {}", holder.record.title))
+        }
+        None => Err(AppError::Validation(
+            "The record still has copies, remove them first".to_string(),
+        ))?,
+    }
+}
+
+fn lend(settings: &Settings, database: &mut Database, options: &Options) -> Result<()> {
+    let name = options.arg(2, "borrower")?;
+    let due = match &options.due {
+        Some(due) => NaiveDate::parse_from_str(due, "%Y-%m-%d")
+            .map_err(|_| usage(&format!("Invalid due date \"{}\"", due)))?,
+        None => Loan::default_due(),
+    };
+    let mut borrowers = Borrowers::new(&settings.borrowers_path)?;
+    let (record_id, item_id) = (options.id(0, "release-id")?, options.id(1, "item-id")?);
+    let item = borrowers.registering(|borrowers| {
+        database.modify_item(record_id, item_id, |item| {
+            item.lend(borrowers, name, due, options.message.clone())
+        })
+    })?;
+    options.print(&item, || println!("Lent to {} until {}", name, due))
+}
+
+fn return_item(settings: &Settings, database: &mut Database, options: &Options) -> Result<()> {
+    let borrowers = Borrowers::new(&settings.borrowers_path)?;
+    let item = database.modify_item(
+        options.id(0, "release-id")?,
+        options.id(1, "item-id")?,
+        |item| item.return_loan(&borrowers),
+    )?;
+    options.print(&item, || println!("Returned"))
+}
+
+fn contact(settings: &Settings, options: &Options) -> Result<()> {
+    let mut borrowers = Borrowers::new(&settings.borrowers_path)?;
+    let name = options.arg(0, "borrower")?;
+    let borrower = borrowers.set_contact(name, &options.args[1..].join(" "))?;
+    options.print(borrower, || match &borrower.contact {
+        Some(contact) => println!("{}: {}", borrower.name, contact),
+        None => println!("{}: no contact details", borrower.name),
+    })
+}
+
+fn export(settings: &Settings, database: &Database, options: &Options) -> Result<()> {
+    let format = match (&options.format, &options.output) {
+        (Some(name), _) => ExportFormat::parse(name)
+            .ok_or_else(|| usage(&format!("Unknown export format \"{}\"", name)))?,
+        (None, Some(output)) => ExportFormat::from_path(output).unwrap_or(ExportFormat::Json),
+        (None, None) => ExportFormat::Json,
+    };
+    if format == ExportFormat::Json && options.columns.is_some() {
+        Err(usage(
+            "--columns only applies to csv, markdown and html exports",
+        ))?
+    }
+    let query = SearchQuery::parse(&options.args.join(" "), &settings.custom_fields);
+    let holders = database.search(&query, None);
+    let columns: Vec<String> = match &options.columns {
+        Some(columns) => columns.split(',').map(|c| c.trim().to_string()).collect(),
+        None => DEFAULT_COLUMNS.iter().map(|c| c.to_string()).collect(),
+    };
+    let table = || ExportTable::new(&holders, &query, &columns, options.all);
+    let output = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&holders)?,
+        ExportFormat::Csv => table()?.to_csv(),
+        ExportFormat::Markdown => table()?.to_markdown(),
+        ExportFormat::Html => table()?.to_html("Record collection"),
+    };
+    match &options.output {
+        Some(path) => {
+            fs::write(path, output).map_err(|e| AppError::file(path, e))?;
+            eprintln!("Catalog written to {}", path);
+        }
+        None => print!("{}", output),
+    }
+    Ok(())
+}
+
+fn labels(settings: &Settings, database: &Database, options: &Options) -> Result<()> {
+    let output = options
+        .output
+        .as_ref()
+        .ok_or_else(|| usage("labels needs -o <file.pdf> or -o <file.svg>"))?;
+    let layout = LabelLayout::find(options.layout.as_deref().unwrap_or("L7160"))?;
+    let skip = match &options.skip {
+        Some(skip) => skip
+            .parse()
+            .map_err(|_| usage(&format!("--skip must be a number, got \"{}\"", skip)))?,
+        None => 0,
+    };
+    let query = SearchQuery::parse(&options.args.join(" "), &settings.custom_fields);
+    let holders = database.search(&query, None);
+    let labels = sleeve_labels(&holders, &query, options.all);
+    if labels.is_empty() {
+        Err(AppError::NotFound("No copies found".to_string()))?
+    }
+
+    if output.to_ascii_lowercase().ends_with(".svg") {
+        let sheets = to_svg(&labels, layout, skip)?;
+        let count = sheets.len();
+        for (i, sheet) in sheets.into_iter().enumerate() {
+            let path = match count {
+                1 => output.clone(),
+                _ => format!(
+                    "{}-{}.svg",
+                    Path::new(output).with_extension("").display(),
+                    i + 1
+                ),
+            };
+            fs::write(&path, sheet).map_err(|e| AppError::file(&path, e))?;
+            eprintln!("Sheet written to {}", path);
+        }
+    } else {
+        fs::write(output, to_pdf(&labels, layout, skip)?).map_err(|e| AppError::file(output, e))?;
+        eprintln!("{} labels written to {}", labels.len(), output);
+    }
+    Ok(())
+}
+
+fn lookup(database: &Database, options: &Options) -> Result<()> {
+    let code = options.arg(0, "code")?;
+    let (record_id, item_id) =
+        parse_label_code(code).ok_or_else(|| usage(&format!("Not a label code: \"{}\"", code)))?;
+    let holder = database.holder(record_id)?;
+    let item = holder
+        .items
+        .iter()
+        .find(|i| i.id == item_id)
+        .ok_or_else(|| AppError::NotFound(format!("No copy #{}", item_id)))?;
+    options.print(item, || {
+        println!("{}", holder_line(holder));
+        println!("{}", item_line(item));
+    })
+}
+
+fn site(settings: &Settings, database: &Database, options: &Options) -> Result<()> {
+    let dir = options
+        .output
+        .as_ref()
+        .ok_or_else(|| usage("site needs -o <directory>"))?;
+    let query = SearchQuery::parse(&options.args.join(" "), &settings.custom_fields);
+    let count = generate_site(
+        &database.search(&query, None),
+        &query,
+        &CoverStore::new(&settings.covers_path)?,
+        dir,
+        options.title.as_deref().unwrap_or("Record catalog"),
+    )?;
+    if count == 0 {
+        Err(AppError::NotFound("No records found".to_string()))?
+    }
+    eprintln!("{} record pages written to {}", count, dir);
+    Ok(())
+}
+
+fn import(database: &mut Database, options: &Options) -> Result<()> {
+    let other = Database::read_data(options.arg(0, "file")?)?;
+    let report = match options.dry_run {
+        true => merge::merge(database.holders(), &other).1,
+        false => database.import(&other)?,
+    };
+    options.print(&report, || println!("{}", report))
+}
+
+fn stats(database: &Database, options: &Options) -> Result<()> {
+    let stats = collection_stats(database.holders());
+    options.print(&stats, || {
+        println!(
+            "{} records, {} copies, {} on loan",
+            stats.holders, stats.copies, stats.on_loan
+        );
+        for (title, counts) in [
+            ("Genres", &stats.by_genre),
+            ("Formats", &stats.by_format),
+            ("Decades", &stats.by_decade),
+            ("Top artists", &stats.top_artists),
+        ] {
+            println!("\n{}:", title);
+            for (name, count) in counts.iter().take(10) {
+                println!("  {:<30} {}", name, count);
+            }
+        }
+    })
+}
+
+/// `merge <left.json> <right.json> (-o <output.json> | --dry-run)`
+fn run_merge(options: &Options) -> Result<()> {
+    let (left, right) = match options.args.as_slice() {
+        [left, right] => (left, right),
+        _ => Err(usage("merge needs two catalog files"))?,
+    };
+
+    let (merged, report) = merge::merge(&Database::read_data(left)?, &Database::read_data(right)?);
+    if !options.dry_run {
+        let output = options
+            .output
+            .as_ref()
+            .ok_or_else(|| usage("merge needs -o <output.json> or --dry-run"))?;
+        Database::write_data(output, &merged)?;
+        eprintln!("Merged catalog written to {}", output);
+    }
+    options.print(&report, || println!("{}", report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn flags_are_read_anywhere_on_the_line() {
+        let options =
+            Options::parse(&args("12 --json 3 Bob --due 2030-05-01 --collection jazz")).unwrap();
+        assert!(options.json);
+        assert!(!options.dry_run);
+        assert_eq!(options.args, vec!["12", "3", "Bob"]);
+        assert_eq!(options.due.as_deref(), Some("2030-05-01"));
+        assert_eq!(options.collection.as_deref(), Some("jazz"));
+        assert_eq!(options.id(0, "release-id").unwrap(), 12);
+        assert_eq!(options.arg(2, "borrower").unwrap(), "Bob");
+
+        assert!(matches!(options.arg(3, "message"), Err(CliError::Usage(_))));
+        assert!(matches!(options.id(2, "item-id"), Err(CliError::Usage(_))));
+        assert!(matches!(
+            Options::parse(&args("12 --due")),
+            Err(CliError::Usage(message)) if message == "--due needs a value"
+        ));
+        assert!(matches!(
+            Options::parse(&args("12 --verbose")),
+            Err(CliError::Usage(message)) if message == "Unknown flag --verbose"
+        ));
+        // Single dash arguments other than -o are positional, e.g. negative numbers.
+        assert_eq!(Options::parse(&args("-1")).unwrap().args, vec!["-1"]);
+    }
+
+    #[test]
+    fn errors_map_to_exit_codes() {
+        assert_eq!(exit_code(&usage("Missing command")), EXIT_USAGE);
+        assert_eq!(
+            exit_code(&AppError::NotFound("Record not found".to_string()).into()),
+            EXIT_NOT_FOUND
+        );
+        assert_eq!(
+            exit_code(&AppError::Storage("Disk full".to_string()).into()),
+            EXIT_FAILURE
+        );
+        assert!(matches!(run_command(&[]), Err(CliError::Usage(_))));
+        assert!(run_command(&args("help")).is_ok());
+    }
+}
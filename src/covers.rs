@@ -0,0 +1,173 @@
+//! Downloaded cover images and how the terminal can show them.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::models::{
+    error::{AppError, Result},
+    record::Record,
+};
+
+/// Cover images kept next to the catalog. Files are named after the SHA-256 of their
+/// content so an image shared by several releases is stored once, `index.json` maps
+/// release ids to those hashes.
+pub struct CoverStore {
+    dir: PathBuf,
+    index: HashMap<i64, String>,
+}
+
+impl CoverStore {
+    /// Opens the cover directory and its index, both are created on the first download.
+    pub fn new(dir: &str) -> Result<Self> {
+        let dir = PathBuf::from(dir);
+        let index = match fs::read_to_string(dir.join("index.json")) {
+            Ok(data) => serde_json::from_str(&data)?,
+            Err(_) => HashMap::new(),
+        };
+        Ok(CoverStore { dir, index })
+    }
+
+    /// The stored cover of a release, if it was downloaded.
+    pub fn path(&self, record_id: i64) -> Option<PathBuf> {
+        let hash = self.index.get(&record_id)?;
+        Some(self.blob_path(hash)).filter(|p| p.exists())
+    }
+
+    /// Downloads the primary image of the record, or the first one when none is primary.
+    pub fn fetch(&mut self, record: &Record) -> Result<PathBuf> {
+        let image = record
+            .images
+            .iter()
+            .find(|i| i.type_ == "primary")
+            .or_else(|| record.images.first())
+            .ok_or_else(|| AppError::NotFound("Record has no cover image".to_string()))?;
+        if image.uri.is_empty() {
+            Err(AppError::NotFound(
+                "Cover image URL not available".to_string(),
+            ))?
+        }
+
+        let bytes = download(&image.uri)?;
+        let hash = content_hash(&bytes);
+        let path = self.blob_path(&hash);
+        if !path.exists() {
+            let dir = path.parent().unwrap_or(&self.dir);
+            fs::create_dir_all(dir).map_err(|e| AppError::file(dir, e))?;
+            fs::write(&path, &bytes).map_err(|e| AppError::file(&path, e))?;
+        }
+        self.index.insert(record.id, hash);
+        self.save()?;
+        Ok(path)
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(&hash[..2]).join(hash)
+    }
+
+    fn save(&self) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|e| AppError::file(&self.dir, e))?;
+        let path = self.dir.join("index.json");
+        fs::write(&path, serde_json::to_string(&self.index)?).map_err(|e| AppError::file(&path, e))
+    }
+}
+
+/// Lowercase hex SHA-256 of an image, its file name in the store.
+fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn download(url: &str) -> Result<Vec<u8>> {
+    let mut res = reqwest::Client::new()
+        .get(url)
+        .header(reqwest::header::USER_AGENT, "RecordCatalog")
+        .send()?;
+    if !res.status().is_success() {
+        Err(AppError::Network(format!(
+            "Cover download failed: {}",
+            res.status()
+        )))?
+    }
+    let mut bytes = vec![];
+    res.read_to_end(&mut bytes)
+        .map_err(|e| AppError::Network(format!("Cover download failed: {}", e)))?;
+    Ok(bytes)
+}
+
+/// Decodes a stored cover, shrunk so it is cheap to redraw every frame.
+pub fn load_thumbnail(path: &Path) -> Result<RgbImage> {
+    Ok(image::open(path)?.thumbnail(256, 256).to_rgb8())
+}
+
+/// How covers are drawn in the terminal.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphicsProtocol {
+    /// Two pixels per cell using "▀" with separate foreground and background colours.
+    HalfBlocks,
+    Kitty,
+    Sixel,
+    None,
+}
+
+impl GraphicsProtocol {
+    /// Guesses the protocol from the environment, terminals that can't be
+    /// recognised get half blocks which work with any true colour terminal.
+    pub fn detect() -> Self {
+        let var = |name: &str| std::env::var(name).unwrap_or_default().to_lowercase();
+        let term = var("TERM");
+        if std::env::var("KITTY_WINDOW_ID").is_ok()
+            || term.contains("kitty")
+            || var("TERM_PROGRAM") == "wezterm"
+        {
+            GraphicsProtocol::Kitty
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            GraphicsProtocol::Sixel
+        } else {
+            GraphicsProtocol::HalfBlocks
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covers_are_stored_by_content() {
+        let hash = content_hash(b"abc");
+        assert_eq!(
+            hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let dir = std::env::temp_dir().join(format!("covers-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("index.json"),
+            format!("{{\"1\": \"{0}\", \"2\": \"{0}\", \"3\": \"00ff\"}}", hash),
+        )
+        .unwrap();
+        let blob = dir.join("ba").join(&hash);
+        fs::create_dir_all(blob.parent().unwrap()).unwrap();
+        fs::write(&blob, b"abc").unwrap();
+
+        let store = CoverStore::new(dir.to_str().unwrap()).unwrap();
+        assert_eq!(store.path(1), Some(blob.clone()));
+        assert_eq!(store.path(2), Some(blob));
+        // Indexed covers whose file is gone count as not downloaded.
+        assert_eq!(store.path(3), None);
+        assert_eq!(store.path(4), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
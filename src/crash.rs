@@ -0,0 +1,107 @@
+use std::{
+    backtrace::Backtrace,
+    collections::VecDeque,
+    fs::OpenOptions,
+    io::Write,
+    panic,
+    sync::{Mutex, OnceLock},
+};
+
+use chrono::{DateTime, Local};
+
+use crate::gui::terminal;
+
+/// How many of the latest actions end up in a crash report.
+const KEPT_ACTIONS: usize = 50;
+
+static ACTIONS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static LOG_PATH: OnceLock<String> = OnceLock::new();
+
+/// Remembers an action of the user for the crash report.
+pub fn record(action: String) {
+    if let Ok(mut actions) = ACTIONS.lock() {
+        if actions.len() == KEPT_ACTIONS {
+            actions.pop_front();
+        }
+        actions.push_back(format!("{} {}", Local::now().format("%H:%M:%S"), action));
+    }
+}
+
+/// Restores the terminal and writes a crash report to `log_path` before the panic
+/// message is printed, so it shows up in a usable shell.
+pub fn install_panic_hook(log_path: &str) {
+    let _ = LOG_PATH.set(log_path.to_string());
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        terminal::restore();
+        let backtrace = Backtrace::force_capture();
+        let report = write_report(&format!("{}\n\n{}", info, backtrace));
+        default_hook(info);
+        if let Some(path) = report {
+            eprintln!("Crash report written to {}", path);
+        }
+    }));
+}
+
+/// Appends the reason and the latest actions to the log file. Returns the path
+/// when the report was written.
+pub fn write_report(reason: &str) -> Option<&'static str> {
+    let path = LOG_PATH.get()?;
+    let actions = match ACTIONS.lock() {
+        Ok(actions) => actions.iter().cloned().collect::<Vec<_>>(),
+        Err(poisoned) => poisoned.get_ref().iter().cloned().collect(),
+    };
+    let report = format_report(reason, &actions, Local::now());
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .ok()?;
+    file.write_all(report.as_bytes()).ok()?;
+    Some(path)
+}
+
+/// A report entry: when, why and what the user did before.
+fn format_report(reason: &str, actions: &[String], time: DateTime<Local>) -> String {
+    format!(
+        "=== Crash at {} ===\n{}\n\nLast actions:\n{}\n\n",
+        time.format("%Y-%m-%d %H:%M:%S"),
+        reason.trim_end(),
+        match actions.is_empty() {
+            true => "  none".to_string(),
+            false => actions
+                .iter()
+                .map(|a| format!("  {}", a))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn reports_list_the_latest_actions() {
+        let time = Local.with_ymd_and_hms(2026, 3, 1, 14, 5, 9).unwrap();
+        assert_eq!(
+            format_report("boom\n\n", &[], time),
+            "=== Crash at 2026-03-01 14:05:09 ===\nboom\n\nLast actions:\n  none\n\n"
+        );
+        let actions = vec!["14:05:01 Search".to_string(), "14:05:08 Enter".to_string()];
+        assert_eq!(
+            format_report("boom", &actions, time),
+            "=== Crash at 2026-03-01 14:05:09 ===\nboom\n\nLast actions:\n  14:05:01 Search\n  14:05:08 Enter\n\n"
+        );
+
+        for i in 0..KEPT_ACTIONS + 5 {
+            record(format!("action {}", i));
+        }
+        let actions = ACTIONS.lock().unwrap();
+        assert_eq!(actions.len(), KEPT_ACTIONS);
+        assert!(actions.front().unwrap().ends_with(" action 5"));
+    }
+}
@@ -1,48 +1,112 @@
-use std::fs;
+//! The collection stored as a JSON file, with a lock against concurrent writers.
 
-use crate::models::{
-    error::Result,
-    item_holder::{Item, ItemHolder},
-    record::Record,
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    process,
 };
 
+use crate::{
+    merge::{self, MergeReport},
+    models::{
+        annotations::Annotations,
+        error::{AppError, Result},
+        item_holder::{Item, ItemEvent, ItemEventType, ItemHolder, ItemState},
+        record::Record,
+    },
+    search::SearchQuery,
+};
+
+/// The collection loaded from its file. Every change is written back immediately.
 pub struct Database {
     file_path: String,
-    pub data: Vec<ItemHolder>,
+    lock_path: String,
+    stamp: FileStamp,
+    data: Vec<ItemHolder>,
+}
+
+/// What the database file looked like the last time it was read or written. Only the
+/// content counts, modification times are too coarse on some filesystems to notice a
+/// write made within the same second.
+#[derive(Clone, Copy, PartialEq)]
+struct FileStamp {
+    len: usize,
+    hash: u64,
 }
 
 impl Database {
+    /// Opens the database at `file_path`, failing when another process holds its lock.
     pub fn new(file_path: &str) -> Result<Self> {
+        let lock_path = file_path.to_string() + ".lock";
+        Database::acquire_lock(&lock_path)?;
+        let loaded = Database::initial_load(file_path)
+            .and_then(|data| Ok((data, Database::read_stamp(file_path)?)));
+        let (data, stamp) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                let _ = fs::remove_file(&lock_path);
+                return Err(e);
+            }
+        };
+
         Ok(Database {
             file_path: file_path.to_string(),
-            data: Database::initial_load(file_path)?,
+            stamp,
+            lock_path,
+            data,
         })
     }
 
-    #[allow(dead_code)]
-    pub fn contains(&self, record: &Record) -> bool {
-        return self
-            .data
-            .iter()
-            .map(|h| &h.record)
-            .collect::<Vec<_>>()
-            .contains(&record);
+    /// Whether the file was changed by someone else since it was last read or written.
+    pub fn has_external_changes(&self) -> Result<bool> {
+        Ok(Database::read_stamp(&self.file_path)? != self.stamp)
     }
 
-    pub fn search(&self, query: &str, item_holder: Option<Record>) -> Vec<ItemHolder> {
-        let results = self.data.iter().filter(|r| {
-            let json = serde_json::to_string(&r).unwrap().to_ascii_lowercase();
-            query
-                .to_ascii_lowercase()
-                .split_ascii_whitespace()
-                .all(|q| json.contains(q))
-        });
+    /// Drops the in-memory data in favour of what is currently on disk.
+    pub fn reload(&mut self) -> Result<()> {
+        self.data = Database::initial_load(&self.file_path)?;
+        self.stamp = Database::read_stamp(&self.file_path)?;
+        Ok(())
+    }
+
+    /// Writes the in-memory data even if the file was changed by someone else.
+    pub fn overwrite(&mut self) -> Result<()> {
+        self.write()
+    }
+
+    /// Merges what is currently on disk into the in-memory data and saves the result.
+    pub fn merge_external(&mut self) -> Result<MergeReport> {
+        let external = Database::read_data(&self.file_path)?;
+        let (data, report) = merge::merge(&self.data, &external);
+        self.data = data;
+        self.write()?;
+        Ok(report)
+    }
+
+    /// Merges another catalog into the collection and saves the result.
+    pub fn import(&mut self, other: &[ItemHolder]) -> Result<MergeReport> {
+        let (data, report) = merge::merge(&self.data, other);
+        self.data = data;
+        self.save()?;
+        Ok(report)
+    }
+
+    /// All records of the collection with their copies, in the order of the file.
+    pub fn holders(&self) -> &[ItemHolder] {
+        &self.data
+    }
+
+    /// Records matching a [`SearchQuery`]. A given `item_holder` record is listed first
+    /// even when it doesn't match or isn't in the collection yet.
+    pub fn search(&self, query: &SearchQuery, item_holder: Option<Record>) -> Vec<ItemHolder> {
+        let results = self.data.iter().filter(|r| query.matches(r));
 
         match item_holder {
             Some(record) => {
                 let mut res: Vec<_> = results
                     .filter(|ih| ih.record.id != record.id)
-                    .map(|ih| ih.clone())
+                    .cloned()
                     .collect();
                 res.insert(
                     0,
@@ -53,19 +117,24 @@ impl Database {
                 );
                 res
             }
-            None => results.map(|i| i.clone()).collect(),
+            None => {
+                let mut res: Vec<_> = results.cloned().collect();
+                query.sort(&mut res);
+                res
+            }
         }
     }
 
+    /// Whether a record with the id is saved in the collection.
     pub fn contains_id(&self, id: i64) -> bool {
-        return self
-            .data
+        self.data
             .iter()
             .map(|r| r.record.id)
             .collect::<Vec<_>>()
-            .contains(&id);
+            .contains(&id)
     }
 
+    /// Saves a record with a new copy, or adds a copy when the record is already saved.
     pub fn add(&mut self, record: Record) -> Result<ItemHolder> {
         let holder = match self
             .data
@@ -87,20 +156,236 @@ impl Database {
         Ok(holder)
     }
 
+    /// Next free id in the local (negative) namespace used for manually entered records.
+    pub fn next_local_id(&self) -> i64 {
+        self.data
+            .iter()
+            .map(|ih| ih.record.id)
+            .filter(|id| *id < 0)
+            .min()
+            .unwrap_or(0)
+            - 1
+    }
+
+    /// Replaces a manually entered record with a Discogs release, keeping its items,
+    /// tags, fields, rating and notes. Notes are appended when both have their own.
+    pub fn link(&mut self, local: &Record, release: Record) -> Result<ItemHolder> {
+        let previous = self.data.clone();
+        let index = self
+            .data
+            .iter()
+            .position(|ih| &ih.record == local)
+            .ok_or_else(|| {
+                AppError::NotFound("The local record is no longer in the collection".to_string())
+            })?;
+        let local_holder = self.data.remove(index);
+
+        let holder = match self.data.iter_mut().find(|ih| ih.record.id == release.id) {
+            Some(holder) => holder,
+            None => {
+                self.data.push(ItemHolder::new(release.clone()));
+                self.data.last_mut().unwrap()
+            }
+        };
+        for mut item in local_holder.items {
+            item.id = holder.next_item_id();
+            item.events.push(ItemEvent::with_message(
+                ItemEventType::Message,
+                format!("Linked to Discogs release {}", release.id),
+            ));
+            holder.items.push(item);
+        }
+        holder.annotations.merge(&local_holder.annotations);
+        holder.rating = holder.rating.or(local_holder.rating);
+        holder.notes = match (holder.notes.take(), local_holder.notes) {
+            (Some(notes), Some(local)) if notes != local => Some(format!("{}\n\n{}", notes, local)),
+            (notes, local) => notes.or(local),
+        };
+        let holder = holder.clone();
+
+        if let Err(e) = self.save() {
+            self.data = previous;
+            return Err(e);
+        }
+        Ok(holder)
+    }
+
+    /// Number of copies still in the collection, lent ones included.
+    pub fn count_copies(&self) -> usize {
+        self.data
+            .iter()
+            .flat_map(|ih| &ih.items)
+            .filter(|i| i.is_in_collection())
+            .count()
+    }
+
+    /// Adds a copy coming from another collection under a fresh item id.
+    pub fn receive_item(&mut self, record: Record, mut item: Item) -> Result<ItemHolder> {
+        let previous = self.data.clone();
+        let holder = match self.data.iter_mut().find(|ih| ih.record.id == record.id) {
+            Some(holder) => holder,
+            None => {
+                self.data.push(ItemHolder::new(record));
+                self.data.last_mut().unwrap()
+            }
+        };
+        item.id = holder.next_item_id();
+        holder.items.push(item);
+        let holder = holder.clone();
+
+        if let Err(e) = self.save() {
+            self.data = previous;
+            return Err(e);
+        }
+        Ok(holder)
+    }
+
+    /// Replaces the metadata of a saved record, its id has to stay the same.
+    pub fn update_record(&mut self, old: &Record, record: Record) -> Result<()> {
+        if old.id != record.id {
+            Err(AppError::Validation(
+                "Record id can't be changed".to_string(),
+            ))?
+        }
+        self.data
+            .iter_mut()
+            .find(|ih| &ih.record == old)
+            .ok_or_else(|| {
+                AppError::NotFound(format!("{} is no longer in the collection", old.title))
+            })?
+            .record = record;
+        self.save()
+    }
+
+    /// The holder of a record, `AppError::NotFound` when the record isn't in the collection.
+    pub fn holder(&self, record_id: i64) -> Result<&ItemHolder> {
+        self.data
+            .iter()
+            .find(|ih| ih.record.id == record_id)
+            .ok_or_else(|| AppError::NotFound(format!("No record with id {}", record_id)))
+    }
+
+    /// Applies `update` to a copy of the item and saves it, the stored item is only
+    /// replaced once the update succeeded. Every change to a single copy, from the
+    /// app, the command line or the API, goes through here.
+    pub fn modify_item<F>(&mut self, record_id: i64, item_id: i64, update: F) -> Result<Item>
+    where
+        F: FnOnce(&mut Item) -> Result<()>,
+    {
+        let holder = self.holder(record_id)?;
+        let mut item = holder
+            .items
+            .iter()
+            .find(|i| i.id == item_id)
+            .cloned()
+            .ok_or_else(|| {
+                AppError::NotFound(format!("No copy #{} of {}", item_id, holder.record.title))
+            })?;
+        let record = holder.record.clone();
+        update(&mut item)?;
+        self.update_item(&record, item.clone())?;
+        Ok(item)
+    }
+
+    /// Moves a copy into the `destination` collection under a fresh id, recording the
+    /// move on both sides. The copy is marked as transferred here first, so a failed
+    /// save never leaves it in both collections, and the mark is undone when the
+    /// destination can't be saved. Returns the copy as it stays in this collection.
+    pub fn transfer_item(
+        &mut self,
+        record_id: i64,
+        item_id: i64,
+        destination: &mut Database,
+        from: &str,
+        to: &str,
+    ) -> Result<Item> {
+        let record = self.holder(record_id)?.record.clone();
+        let item = self.modify_item(record_id, item_id, |item| {
+            if item.state() != ItemState::InCollection {
+                Err(AppError::Validation(format!("Item is {}", item.state())))?
+            }
+            item.events.push(ItemEvent::with_message(
+                ItemEventType::TransferredOut,
+                format!("To {}", to),
+            ));
+            Ok(())
+        })?;
+
+        let mut original = item.clone();
+        original.events.pop();
+        let mut moved = original.clone();
+        moved.events.push(ItemEvent::with_message(
+            ItemEventType::TransferredIn,
+            format!("From {}", from),
+        ));
+        if let Err(e) = destination.receive_item(record, moved) {
+            self.modify_item(record_id, item_id, |item| {
+                *item = original;
+                Ok(())
+            })
+            .map_err(|rollback| {
+                AppError::Storage(format!(
+                    "{}, and marking the copy as still here failed too: {}",
+                    e, rollback
+                ))
+            })?;
+            return Err(e);
+        }
+        Ok(item)
+    }
+
+    /// Replaces the copy with the same id as `item`. Prefer [`Database::modify_item`],
+    /// which applies the change to the stored copy.
     pub fn update_item(&mut self, record: &Record, item: Item) -> Result<()> {
         let old_item = self
             .data
             .iter_mut()
             .find(|ih| &ih.record == record)
-            .ok_or("No item holder matching the current record")?
+            .ok_or_else(|| {
+                AppError::NotFound(format!("{} is no longer in the collection", record.title))
+            })?
             .items
             .iter_mut()
             .find(|i| i.id == item.id)
-            .ok_or("No item holder matching the item id")?;
-        old_item.events = item.events;
+            .ok_or_else(|| {
+                AppError::NotFound(format!(
+                    "Copy #{} of {} no longer exists",
+                    item.id, record.title
+                ))
+            })?;
+        *old_item = item;
+        self.save()
+    }
+
+    /// Replaces the tags and custom fields of a saved record.
+    pub fn annotate_holder(&mut self, record: &Record, annotations: Annotations) -> Result<()> {
+        self.data
+            .iter_mut()
+            .find(|ih| &ih.record == record)
+            .ok_or_else(|| AppError::NotFound("Save the record before tagging it".to_string()))?
+            .annotations = annotations;
+        self.save()
+    }
+
+    /// Sets the rating and review notes of a saved record, `None` clears them.
+    pub fn review_holder(
+        &mut self,
+        record: &Record,
+        rating: Option<u8>,
+        notes: Option<String>,
+    ) -> Result<()> {
+        let holder = self
+            .data
+            .iter_mut()
+            .find(|ih| &ih.record == record)
+            .ok_or_else(|| AppError::NotFound("Save the record before rating it".to_string()))?;
+        holder.rating = rating;
+        holder.notes = notes;
         self.save()
     }
 
+    /// Deletes the copy at `item_index` of the record, e.g. one added by mistake. Copies
+    /// that left the collection should get a sale, trade or loss event instead.
     pub fn remove_holder_item(&mut self, record: &Record, item_index: usize) -> Result<()> {
         let idx = self.data.iter().position(|x| &x.record == record);
 
@@ -113,6 +398,7 @@ impl Database {
         self.save()
     }
 
+    /// Deletes the record, does nothing when it isn't saved.
     pub fn remove_holder(&mut self, record: &Record) -> Result<()> {
         let idx = self.data.iter().position(|x| &x.record == record);
         if let Some(index) = idx {
@@ -121,24 +407,215 @@ impl Database {
         self.save()
     }
 
+    /// Reads a catalog file without taking the lock, e.g. to merge it into another one.
+    pub fn read_data(file_path: &str) -> Result<Vec<ItemHolder>> {
+        let data_string =
+            fs::read_to_string(file_path).map_err(|e| AppError::file(file_path, e))?;
+        serde_json::from_str::<Vec<ItemHolder>>(&data_string)
+            .map_err(|e| AppError::Parse(format!("{} is not a valid catalog: {}", file_path, e)))
+    }
+
+    /// Writes a catalog file without taking the lock, e.g. the result of a merge.
+    pub fn write_data(file_path: &str, data: &[ItemHolder]) -> Result<()> {
+        let data_string = serde_json::to_string(data)?;
+        fs::write(file_path, data_string).map_err(|e| AppError::file(file_path, e))
+    }
+
     fn initial_load(file_path: &str) -> Result<Vec<ItemHolder>> {
-        let mut data = vec![];
         if std::path::Path::new(file_path).exists() {
-            let data_string = fs::read_to_string(file_path)?;
-            data = serde_json::from_str::<Vec<ItemHolder>>(&data_string)?;
+            Database::read_data(file_path)
         } else {
-            let data_string = serde_json::to_string(&data)?;
-            fs::write(file_path, data_string)?;
+            Database::write_data(file_path, &[])?;
+            Ok(vec![])
+        }
+    }
+
+    fn read_stamp(file_path: &str) -> Result<FileStamp> {
+        let content = fs::read(file_path).map_err(|e| AppError::file(file_path, e))?;
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        Ok(FileStamp {
+            len: content.len(),
+            hash: hasher.finish(),
+        })
+    }
+
+    /// Creates an advisory lock file so two instances never write the same database.
+    fn acquire_lock(lock_path: &str) -> Result<()> {
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(lock_path)
+        {
+            Ok(_) => {
+                fs::write(lock_path, process::id().to_string())
+                    .map_err(|e| AppError::file(lock_path, e))?;
+                Ok(())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                Err(AppError::Storage(format!(
+                    "Database is locked by another instance (pid {}), delete {} if it isn't running",
+                    fs::read_to_string(lock_path).unwrap_or_default().trim(),
+                    lock_path
+                )))
+            }
+            Err(e) => Err(AppError::file(lock_path, e)),
+        }
+    }
+
+    fn save(&mut self) -> Result<()> {
+        if self.has_external_changes()? {
+            Err(AppError::ExternalChange(self.file_path.clone()))?
         }
-        return Ok(data);
+        self.write()
     }
 
-    fn save(&self) -> Result<()> {
-        let data_string = serde_json::to_string(&self.data)?;
-        fs::write(&self.file_path, data_string)?;
+    fn write(&mut self) -> Result<()> {
+        Database::write_data(&self.file_path, &self.data)?;
+        self.stamp = Database::read_stamp(&self.file_path)?;
         Ok(())
     }
 }
 
-#[derive(Debug)]
-pub struct FileContainedError(String);
+impl Drop for Database {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.lock_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Opens a database on a fresh temp file holding `data`.
+    fn temp_database(name: &str, data: &[ItemHolder]) -> (Database, String) {
+        let path = std::env::temp_dir().join(format!(
+            "database-test-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap().to_string();
+        Database::write_data(&path, data).unwrap();
+        (Database::new(&path).unwrap(), path)
+    }
+
+    fn rumours() -> ItemHolder {
+        let mut holder = ItemHolder::new(Record {
+            id: 42,
+            title: "Rumours".to_string(),
+            ..Record::default()
+        });
+        holder.items.push(Item::new_with_id(1));
+        holder
+    }
+
+    #[test]
+    fn linking_keeps_tags_rating_and_notes() {
+        let mut local = rumours();
+        local.record.id = -1;
+        local.annotations.tags.push("dj crate".to_string());
+        local.rating = Some(4);
+        local.notes = Some("Side B skips".to_string());
+        let (mut database, path) = temp_database("link", &[local.clone()]);
+
+        let release = Record {
+            id: 42,
+            title: "Rumours".to_string(),
+            ..Record::default()
+        };
+        let linked = database.link(&local.record, release).unwrap();
+        assert!(linked.annotations.has_tag("dj crate"));
+        assert_eq!(linked.rating, Some(4));
+        assert_eq!(linked.notes.as_deref(), Some("Side B skips"));
+        assert_eq!(linked.items.len(), 1);
+        let saved = Database::read_data(&path).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].annotations, linked.annotations);
+        assert_eq!(saved[0].rating, Some(4));
+
+        drop(database);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn failed_links_keep_the_local_record() {
+        let mut local = rumours();
+        local.record.id = -1;
+        let (mut database, path) = temp_database("failed-link", &[local.clone()]);
+        Database::write_data(&path, &[]).unwrap();
+
+        let release = Record {
+            id: 42,
+            ..Record::default()
+        };
+        let result = database.link(&local.record, release);
+        assert!(matches!(result, Err(AppError::ExternalChange(_))));
+        assert_eq!(database.holders().len(), 1);
+        assert_eq!(database.holders()[0].record, local.record);
+        assert_eq!(database.holders()[0].items.len(), 1);
+
+        drop(database);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn transferring_moves_the_copy() {
+        let (mut source, source_path) = temp_database("transfer-source", &[rumours()]);
+        let (mut destination, destination_path) = temp_database("transfer-destination", &[]);
+
+        let item = source
+            .transfer_item(42, 1, &mut destination, "Home", "Office")
+            .unwrap();
+        assert_eq!(item.state(), ItemState::TransferredOut);
+        let saved = Database::read_data(&source_path).unwrap();
+        assert_eq!(saved[0].items[0].state(), ItemState::TransferredOut);
+        let received = Database::read_data(&destination_path).unwrap();
+        assert_eq!(received[0].items[0].state(), ItemState::InCollection);
+
+        drop((source, destination));
+        fs::remove_file(source_path).unwrap();
+        fs::remove_file(destination_path).unwrap();
+    }
+
+    #[test]
+    fn failed_transfers_keep_the_copy_in_the_source() {
+        let (mut source, source_path) = temp_database("rollback-source", &[rumours()]);
+        let (mut destination, destination_path) = temp_database("rollback-destination", &[]);
+        Database::write_data(&destination_path, &[rumours()]).unwrap();
+
+        let result = source.transfer_item(42, 1, &mut destination, "Home", "Office");
+        assert!(matches!(result, Err(AppError::ExternalChange(_))));
+        let item = &source.holder(42).unwrap().items[0];
+        assert_eq!(item.state(), ItemState::InCollection);
+        let saved = Database::read_data(&source_path).unwrap();
+        assert_eq!(saved[0].items[0], *item);
+        assert!(destination.data.is_empty());
+
+        drop((source, destination));
+        fs::remove_file(source_path).unwrap();
+        fs::remove_file(destination_path).unwrap();
+    }
+
+    #[test]
+    fn writes_keeping_the_modification_time_are_noticed() {
+        let (mut database, path) = temp_database("external", &[rumours()]);
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        let mut changed = rumours();
+        changed.notes = Some("Edited elsewhere".to_string());
+        Database::write_data(&path, &[changed]).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        assert!(database.has_external_changes().unwrap());
+        let result =
+            database.modify_item(42, 1, |item| item.add_event(ItemEventType::Played, None));
+        assert!(matches!(result, Err(AppError::ExternalChange(_))));
+
+        drop(database);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,36 +1,125 @@
-use std::io::Read;
+//! Client for the Discogs database and marketplace API.
+
+use std::{
+    io::Read,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
 
 use serde::Deserialize;
 
-use super::models::{error::Result, query::DiscogsSearchResponse, record::Record};
+use super::{
+    models::{
+        error::{AppError, Result},
+        query::DiscogsSearchResponse,
+        record::Record,
+    },
+    valuation::{MarketplaceStats, PriceSource, PriceSuggestions},
+};
+
+/// Discogs allows 60 authenticated requests a minute.
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
 
+/// Authenticated access to Discogs with a personal token. Clones share the rate limit.
+#[derive(Clone)]
 pub struct DiscogsClient {
     token: String,
+    currency: Option<String>,
+    last_request: Arc<Mutex<Option<Instant>>>,
 }
 
 impl DiscogsClient {
-    pub fn new(token: &str) -> Self {
+    /// A client authenticated with a personal access token. Prices are asked for in
+    /// `currency`, or the one of the account when it is `None`.
+    pub fn new(token: &str, currency: Option<String>) -> Self {
         DiscogsClient {
             token: token.to_string(),
+            currency,
+            last_request: Arc::new(Mutex::new(None)),
         }
     }
-    fn discogs_request<T: for<'a> Deserialize<'a>>(url: &str) -> Result<T> {
+
+    /// Waits until the next request is within the rate limit. The slot is booked under
+    /// the lock but slept for after releasing it, so other threads can book theirs meanwhile.
+    fn throttle(&self) {
+        let wait = {
+            let mut last_request = match self.last_request.lock() {
+                Ok(last_request) => last_request,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let now = Instant::now();
+            let slot = match *last_request {
+                Some(last) => (last + REQUEST_INTERVAL).max(now),
+                None => now,
+            };
+            *last_request = Some(slot);
+            slot - now
+        };
+        thread::sleep(wait);
+    }
+
+    fn discogs_request<T: for<'a> Deserialize<'a>>(&self, url: &str) -> Result<T> {
+        self.throttle();
         let mut res = reqwest::get(url)?;
+        match res.status().as_u16() {
+            200..=299 => {}
+            401 | 403 => Err(AppError::Network(
+                "Discogs rejected the request, check discogs_key in settings.json".to_string(),
+            ))?,
+            404 => Err(AppError::NotFound("Not found on Discogs".to_string()))?,
+            429 => Err(AppError::Network(
+                "Too many requests to Discogs, try again in a minute".to_string(),
+            ))?,
+            _ => Err(AppError::Network(format!(
+                "Discogs answered {}",
+                res.status()
+            )))?,
+        }
         let mut body = String::new();
-        res.read_to_string(&mut body)?;
-        Ok(serde_json::from_str::<T>(&body)?)
+        res.read_to_string(&mut body)
+            .map_err(|e| AppError::Network(format!("Reading the Discogs answer failed: {}", e)))?;
+        serde_json::from_str::<T>(&body)
+            .map_err(|e| AppError::Parse(format!("Unexpected answer from Discogs: {}", e)))
     }
 
+    /// Searches the Discogs database.
     pub fn query(&self, query_: &str) -> Result<DiscogsSearchResponse> {
         let url = format!(
             "https://api.discogs.com/database/search?q={}&token={}",
             query_, self.token
         );
-        Self::discogs_request(&url)
+        self.discogs_request(&url)
     }
 
+    /// Fetches the full release, including tracks and images.
     pub fn get_release(&self, id: i64) -> Result<Record> {
-        let url = format!("https://api.discogs.com/releases/{}", id);
-        Self::discogs_request(&url)
+        // Image URLs are only included for authenticated requests.
+        let url = format!(
+            "https://api.discogs.com/releases/{}?token={}",
+            id, self.token
+        );
+        self.discogs_request(&url)
+    }
+}
+
+impl PriceSource for DiscogsClient {
+    fn price_suggestions(&self, release_id: i64) -> Result<PriceSuggestions> {
+        let url = format!(
+            "https://api.discogs.com/marketplace/price_suggestions/{}?token={}",
+            release_id, self.token
+        );
+        self.discogs_request(&url)
+    }
+
+    fn marketplace_stats(&self, release_id: i64) -> Result<MarketplaceStats> {
+        let mut url = format!(
+            "https://api.discogs.com/marketplace/stats/{}?token={}",
+            release_id, self.token
+        );
+        if let Some(currency) = &self.currency {
+            url += &format!("&curr_abbr={}", currency);
+        }
+        self.discogs_request(&url)
     }
 }
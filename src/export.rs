@@ -0,0 +1,337 @@
+//! Tables of copies written as CSV, Markdown or HTML reports.
+
+use crate::{
+    models::{
+        error::{AppError, Result},
+        item_holder::{grade_code, Item, ItemHolder},
+    },
+    search::SearchQuery,
+    valuation::format_decimal,
+};
+
+/// Output formats of the `export` command.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
+    Html,
+}
+
+impl ExportFormat {
+    /// The format called `name`, e.g. "csv" or "md".
+    pub fn parse(name: &str) -> Option<ExportFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(ExportFormat::Json),
+            "csv" => Some(ExportFormat::Csv),
+            "md" | "markdown" => Some(ExportFormat::Markdown),
+            "html" | "htm" => Some(ExportFormat::Html),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the extension of the output file.
+    pub fn from_path(path: &str) -> Option<ExportFormat> {
+        ExportFormat::parse(path.rsplit_once('.')?.1)
+    }
+}
+
+/// Every column that can be exported, custom fields are selected with `field:<name>`.
+pub const COLUMNS: [&str; 20] = [
+    "id",
+    "artist",
+    "title",
+    "year",
+    "label",
+    "catno",
+    "format",
+    "genres",
+    "styles",
+    "item",
+    "state",
+    "media",
+    "sleeve",
+    "location",
+    "tags",
+    "fields",
+    "purchased",
+    "source",
+    "price",
+    "currency",
+];
+
+/// Columns exported when none are asked for.
+pub const DEFAULT_COLUMNS: [&str; 14] = [
+    "id", "artist", "title", "year", "label", "catno", "format", "item", "media", "sleeve",
+    "location", "tags", "price", "currency",
+];
+
+/// Rows of the export, one per copy.
+pub struct ExportTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ExportTable {
+    /// Collects the copies of `holders` that pass the item filters of `query`,
+    /// copies that left the collection are only included with `all`.
+    pub fn new(
+        holders: &[ItemHolder],
+        query: &SearchQuery,
+        columns: &[String],
+        all: bool,
+    ) -> Result<ExportTable> {
+        for column in columns {
+            if !COLUMNS.contains(&column.as_str()) && !column.starts_with("field:") {
+                Err(AppError::Validation(format!(
+                    "Unknown column \"{}\", expected field:<name> or one of {}",
+                    column,
+                    COLUMNS.join(", ")
+                )))?
+            }
+        }
+        let rows = holders
+            .iter()
+            .flat_map(|holder| {
+                holder
+                    .items
+                    .iter()
+                    .filter(|item| all || item.is_in_collection())
+                    .filter(|item| query.matches_item(holder, item))
+                    .map(move |item| columns.iter().map(|c| cell(c, holder, item)).collect())
+            })
+            .collect();
+        Ok(ExportTable {
+            columns: columns.to_vec(),
+            rows,
+        })
+    }
+
+    /// The table as CSV with a header row.
+    pub fn to_csv(&self) -> String {
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .map(|c| csv_escape(c))
+                .collect::<Vec<_>>()
+                .join(",")
+                + "\r\n"
+        };
+        std::iter::once(line(&self.columns))
+            .chain(self.rows.iter().map(|r| line(r)))
+            .collect()
+    }
+
+    /// The table as a Markdown table.
+    pub fn to_markdown(&self) -> String {
+        let line = |cells: &[String]| {
+            format!(
+                "| {} |\n",
+                cells
+                    .iter()
+                    .map(|c| c.replace('|', "\\|").replace('\n', " "))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            )
+        };
+        let separator = line(&vec!["---".to_string(); self.columns.len()]);
+        std::iter::once(line(&self.columns))
+            .chain(std::iter::once(separator))
+            .chain(self.rows.iter().map(|r| line(r)))
+            .collect()
+    }
+
+    /// A single page with the table, a search box and sortable columns, without external files.
+    pub fn to_html(&self, title: &str) -> String {
+        let row = |tag: &str, cells: &[String]| {
+            let cells: String = cells
+                .iter()
+                .map(|c| format!("<{tag}>{}</{tag}>", html_escape(c), tag = tag))
+                .collect();
+            format!("<tr>{}</tr>\n", cells)
+        };
+        let body: String = self.rows.iter().map(|r| row("td", r)).collect();
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+input {{ padding: 0.4em; width: 20em; margin-bottom: 1em; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }}
+th {{ background: #eee; cursor: pointer; user-select: none; }}
+th.asc::after {{ content: " \25B2"; }}
+th.desc::after {{ content: " \25BC"; }}
+tr:nth-child(even) td {{ background: #fafafa; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<input id="search" type="search" placeholder="Search"> <span id="count">{count}</span> copies
+<table>
+<thead>{head}</thead>
+<tbody>
+{body}</tbody>
+</table>
+<script>
+const search = document.getElementById("search");
+const count = document.getElementById("count");
+const body = document.querySelector("tbody");
+const rows = Array.from(body.rows);
+
+search.addEventListener("input", () => {{
+  const words = search.value.toLowerCase().split(/\s+/).filter(w => w);
+  let shown = 0;
+  for (const row of rows) {{
+    const text = row.textContent.toLowerCase();
+    const match = words.every(w => text.includes(w));
+    row.hidden = !match;
+    shown += match;
+  }}
+  count.textContent = shown;
+}});
+
+document.querySelectorAll("th").forEach((th, column) => {{
+  th.addEventListener("click", () => {{
+    const ascending = !th.classList.contains("asc");
+    document.querySelectorAll("th").forEach(h => h.classList.remove("asc", "desc"));
+    th.classList.add(ascending ? "asc" : "desc");
+    const value = row => row.cells[column].textContent;
+    rows.sort((a, b) => {{
+      const x = value(a), y = value(b);
+      const order = x !== "" && y !== "" && !isNaN(x) && !isNaN(y)
+        ? x - y
+        : x.localeCompare(y, undefined, {{ numeric: true }});
+      return ascending ? order : -order;
+    }});
+    rows.forEach(row => body.appendChild(row));
+  }});
+}});
+</script>
+</body>
+</html>
+"#,
+            title = html_escape(title),
+            count = self.rows.len(),
+            head = row("th", &self.columns),
+            body = body
+        )
+    }
+}
+
+fn cell(column: &str, holder: &ItemHolder, item: &Item) -> String {
+    let record = &holder.record;
+    let join = |values: Vec<&str>| values.join(", ");
+    let acquisition = item.acquisition.as_ref();
+    match column {
+        "id" => record.id.to_string(),
+        "artist" => join(record.artists.iter().map(|a| a.name.as_str()).collect()),
+        "title" => record.title.clone(),
+        "year" => match record.year {
+            0 => "".to_string(),
+            year => year.to_string(),
+        },
+        "label" => join(record.labels.iter().map(|l| l.name.as_str()).collect()),
+        "catno" => join(record.labels.iter().map(|l| l.catno.as_str()).collect()),
+        "format" => record
+            .formats
+            .iter()
+            .map(|f| f.get_line())
+            .collect::<Vec<_>>()
+            .join(", "),
+        "genres" => record.genres.join(", "),
+        "styles" => record.styles.join(", "),
+        "item" => item.id.to_string(),
+        "state" => item.state().to_string(),
+        "media" => grade_code(item.media_condition).to_string(),
+        "sleeve" => grade_code(item.sleeve_condition).to_string(),
+        "location" => item
+            .location
+            .as_ref()
+            .map_or("".to_string(), |l| l.to_string()),
+        "tags" => {
+            let mut annotations = item.annotations.clone();
+            annotations.merge(&holder.annotations);
+            annotations.tags.join(", ")
+        }
+        "fields" => {
+            let mut annotations = item.annotations.clone();
+            annotations.merge(&holder.annotations);
+            annotations
+                .fields
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ")
+        }
+        "purchased" => acquisition.map_or("".to_string(), |a| a.date.to_string()),
+        "source" => acquisition.map_or("".to_string(), |a| a.source.clone()),
+        "price" => acquisition.map_or("".to_string(), |a| format_decimal(a.price)),
+        "currency" => acquisition.map_or("".to_string(), |a| a.currency.clone()),
+        column => {
+            let name = column.trim_start_matches("field:");
+            item.annotations
+                .get_field(name)
+                .or_else(|| holder.annotations.get_field(name))
+                .cloned()
+                .unwrap_or_default()
+        }
+    }
+}
+
+fn csv_escape(value: &str) -> String {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+/// Escapes text for HTML content and attribute values.
+pub fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(cells: &[&str]) -> ExportTable {
+        ExportTable {
+            columns: vec!["title".to_string(), "tags".to_string()],
+            rows: vec![cells.iter().map(|c| c.to_string()).collect()],
+        }
+    }
+
+    #[test]
+    fn csv_cells_are_quoted_when_needed() {
+        let csv = table(&["Hello, \"Dolly\"", "plain"]).to_csv();
+        assert_eq!(csv, "title,tags\r\n\"Hello, \"\"Dolly\"\"\",plain\r\n");
+        let csv = table(&["Two\nlines", ""]).to_csv();
+        assert!(csv.ends_with("\"Two\nlines\",\r\n"));
+    }
+
+    #[test]
+    fn markdown_cells_keep_the_table_intact() {
+        let markdown = table(&["A | B", "one\ntwo"]).to_markdown();
+        assert_eq!(
+            markdown,
+            "| title | tags |\n| --- | --- |\n| A \\| B | one two |\n"
+        );
+    }
+
+    #[test]
+    fn html_placeholders_in_values_stay_text() {
+        let html = table(&["{count} <b>{body}</b>", "x"]).to_html("{head} & co");
+        assert!(html.contains("<title>{head} &amp; co</title>"));
+        assert!(html.contains("<td>{count} &lt;b&gt;{body}&lt;/b&gt;</td>"));
+        assert!(html.contains("<span id=\"count\">1</span>"));
+        assert!(html.contains("<thead><tr><th>title</th><th>tags</th></tr>\n</thead>"));
+    }
+}
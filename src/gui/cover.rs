@@ -0,0 +1,265 @@
+use std::io::{Stdout, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::{imageops::FilterType, RgbImage};
+use tui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+use record_catalog::{
+    covers::{load_thumbnail, CoverStore, GraphicsProtocol},
+    models::error::{AppError, Result},
+};
+
+/// Assumed size of a terminal cell in pixels, used to keep the aspect ratio.
+const CELL_WIDTH: u32 = 10;
+const CELL_HEIGHT: u32 = 20;
+
+/// The cover shown in the record detail pane, decoded once per record.
+pub struct CoverView {
+    pub store: CoverStore,
+    pub protocol: GraphicsProtocol,
+    /// Last decoded cover, `None` when the record has none or it couldn't be read.
+    loaded: Option<(i64, Option<RgbImage>)>,
+    /// Where a kitty or sixel image goes in this frame and where it was drawn last.
+    wanted: Option<(i64, Rect)>,
+    shown: Option<(i64, Rect)>,
+}
+
+impl CoverView {
+    pub fn new(store: CoverStore, protocol: GraphicsProtocol) -> Self {
+        CoverView {
+            store,
+            protocol,
+            loaded: None,
+            wanted: None,
+            shown: None,
+        }
+    }
+
+    /// Forgets the decoded cover so a freshly downloaded one is picked up.
+    pub fn reload(&mut self) {
+        self.loaded = None;
+    }
+
+    fn image(&mut self, record_id: i64) -> Option<&RgbImage> {
+        if self.loaded.as_ref().map(|(id, _)| *id) != Some(record_id) {
+            let image = self
+                .store
+                .path(record_id)
+                .and_then(|path| load_thumbnail(&path).ok());
+            self.loaded = Some((record_id, image));
+        }
+        self.loaded.as_ref()?.1.as_ref()
+    }
+
+    pub fn start_frame(&mut self) {
+        self.wanted = None;
+    }
+
+    /// Draws the cover at the top of `area` and returns the space left below it,
+    /// records without a cover get the whole area.
+    pub fn render(
+        &mut self,
+        record_id: i64,
+        rect: &mut Frame<CrosstermBackend<Stdout>>,
+        area: Rect,
+    ) -> Rect {
+        if self.protocol == GraphicsProtocol::None || self.image(record_id).is_none() {
+            return area;
+        }
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                [
+                    Constraint::Length((area.height / 2).min(16)),
+                    Constraint::Min(4),
+                ]
+                .as_ref(),
+            )
+            .split(area);
+        let block = Block::default().borders(Borders::ALL).title("Cover");
+        let inner = block.inner(chunks[0]);
+
+        match self.protocol {
+            GraphicsProtocol::HalfBlocks => {
+                let lines = self
+                    .image(record_id)
+                    .map_or(vec![], |image| half_blocks(image, inner));
+                rect.render_widget(Paragraph::new(lines).block(block), chunks[0]);
+            }
+            _ => {
+                rect.render_widget(block, chunks[0]);
+                self.wanted = Some((record_id, inner));
+            }
+        }
+        chunks[1]
+    }
+
+    /// Whether the kitty or sixel image on screen differs from the one of the last frame.
+    pub fn is_stale(&self) -> bool {
+        self.wanted != self.shown
+    }
+
+    /// Writes the kitty or sixel image over the frame, after removing the previous one.
+    pub fn draw_graphic(&mut self, out: &mut CrosstermBackend<Stdout>) -> Result<()> {
+        if self.protocol == GraphicsProtocol::Kitty && self.shown.is_some() {
+            out.write_all(kitty_clear().as_bytes())
+                .map_err(AppError::terminal)?;
+        }
+        if let Some((record_id, area)) = self.wanted {
+            let protocol = self.protocol;
+            if let Some(image) = self.image(record_id) {
+                let sequence = match protocol {
+                    GraphicsProtocol::Kitty => kitty(image, area),
+                    _ => sixel(image, area),
+                };
+                out.write_all(sequence.as_bytes())
+                    .map_err(AppError::terminal)?;
+            }
+        }
+        out.flush().map_err(AppError::terminal)?;
+        self.shown = self.wanted;
+        Ok(())
+    }
+}
+
+/// Scales the image to fit `area` in pixels of `cell_width` x `cell_height`.
+fn fit(image: &RgbImage, area: Rect, cell_width: u32, cell_height: u32) -> RgbImage {
+    let max_width = area.width as u32 * cell_width;
+    let max_height = area.height as u32 * cell_height;
+    let scale = f64::min(
+        max_width as f64 / image.width() as f64,
+        max_height as f64 / image.height() as f64,
+    );
+    let width = ((image.width() as f64 * scale) as u32).max(1);
+    let height = ((image.height() as f64 * scale) as u32).max(1);
+    image::imageops::resize(image, width, height, FilterType::Triangle)
+}
+
+/// Renders the image with one "▀" per cell, the upper pixel as foreground
+/// and the lower one as background.
+pub fn half_blocks(image: &RgbImage, area: Rect) -> Vec<Spans<'static>> {
+    // A cell is twice as high as wide, so one cell holds a 1x2 pixel block.
+    let image = fit(image, area, 1, 2);
+    let color = |x: u32, y: u32| match image.get_pixel_checked(x, y) {
+        Some(p) => Color::Rgb(p[0], p[1], p[2]),
+        None => Color::Reset,
+    };
+    (0..image.height())
+        .step_by(2)
+        .map(|y| {
+            Spans::from(
+                (0..image.width())
+                    .map(|x| {
+                        Span::styled("▀", Style::default().fg(color(x, y)).bg(color(x, y + 1)))
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect()
+}
+
+/// Kitty graphics protocol escape placing the image over `area`.
+pub fn kitty(image: &RgbImage, area: Rect) -> String {
+    let image = fit(image, area, CELL_WIDTH, CELL_HEIGHT);
+    let data = STANDARD.encode(image.as_raw());
+    let chunks: Vec<_> = data.as_bytes().chunks(4096).collect();
+
+    let mut out = move_to(area);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = (i + 1 < chunks.len()) as u8;
+        let control = match i {
+            0 => format!(
+                "a=T,f=24,s={},v={},q=2,m={}",
+                image.width(),
+                image.height(),
+                more
+            ),
+            _ => format!("m={}", more),
+        };
+        out += &format!("\x1b_G{};{}\x1b\\", control, String::from_utf8_lossy(chunk));
+    }
+    out
+}
+
+/// Removes every image placed with the kitty protocol.
+pub fn kitty_clear() -> String {
+    "\x1b_Ga=d,q=2\x1b\\".to_string()
+}
+
+/// Sixel escape placing the image over `area`, colours are reduced to a 6x6x6 cube.
+pub fn sixel(image: &RgbImage, area: Rect) -> String {
+    let image = fit(image, area, CELL_WIDTH, CELL_HEIGHT);
+    let index = |x: u32, y: u32| {
+        let p = image.get_pixel(x, y);
+        let level = |c: u8| (c as usize * 5 + 127) / 255;
+        level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])
+    };
+
+    let mut out = move_to(area);
+    out += &format!("\x1bPq\"1;1;{};{}", image.width(), image.height());
+    for i in 0..216 {
+        let percent = |level: usize| level * 100 / 5;
+        out += &format!(
+            "#{};2;{};{};{}",
+            i,
+            percent(i / 36),
+            percent(i / 6 % 6),
+            percent(i % 6)
+        );
+    }
+
+    for band in (0..image.height()).step_by(6) {
+        let rows = band..(band + 6).min(image.height());
+        let mut colors: Vec<usize> = rows
+            .clone()
+            .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+            .map(|(x, y)| index(x, y))
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for color in colors {
+            out += &format!("#{}", color);
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..image.width() {
+                let bits = rows
+                    .clone()
+                    .filter(|y| index(x, *y) == color)
+                    .fold(0u8, |bits, y| bits | 1 << (y - band));
+                let c = (63 + bits) as char;
+                run = match run {
+                    Some((r, n)) if r == c => Some((r, n + 1)),
+                    Some((r, n)) => {
+                        out += &sixel_run(r, n);
+                        Some((c, 1))
+                    }
+                    None => Some((c, 1)),
+                };
+            }
+            if let Some((r, n)) = run {
+                out += &sixel_run(r, n);
+            }
+            out += "$";
+        }
+        out += "-";
+    }
+    out + "\x1b\\"
+}
+
+fn sixel_run(c: char, n: usize) -> String {
+    match n {
+        1..=3 => c.to_string().repeat(n),
+        _ => format!("!{}{}", n, c),
+    }
+}
+
+fn move_to(area: Rect) -> String {
+    format!("\x1b[{};{}H", area.y + 1, area.x + 1)
+}
@@ -1,3 +1,5 @@
+pub mod cover;
+pub mod record;
 pub mod render;
 pub mod terminal;
-pub mod record;
+pub mod timeline;
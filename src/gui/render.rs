@@ -1,44 +1,86 @@
-use crate::models::{
-    app::{AppPage, AppPages},
-    item_holder::StatefulItem,
+use record_catalog::{
+    borrowers::Borrowers,
+    covers::GraphicsProtocol,
+    models::{
+        error::{AppError, Result},
+        item_holder::{grade_code, ItemEventType, ItemState},
+    },
+    valuation::{format_amounts, CollectionValue},
+};
+
+use crate::state::{
+    app::{App, AppPage, AppPages, STATS_VIEWS},
     list::StatefulList,
-    record::Record,
+    stateful::{ItemInput, StatefulItem, StatefulItemHolder},
 };
 
-use super::super::models::error::Result;
+use super::timeline::event_spans;
 use std::io::Stdout;
 
+use chrono::Local;
+
 use tui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Tabs, Wrap},
+    widgets::{
+        BarChart, Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Tabs, Wrap,
+    },
     Frame, Terminal,
 };
 
-use super::super::models::app::App;
-
 impl App<'_> {
     pub fn render(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
-        terminal.draw(|rect| {
-            let chunks = self.layout_chunks(rect);
-            self.render_menu(rect, chunks[0]);
-            self.render_main(rect, chunks[1]);
-            self.render_message(rect, chunks[2]);
-        })?;
+        self.cover.start_frame();
+        terminal
+            .draw(|rect| self.render_frame(rect))
+            .map_err(AppError::terminal)?;
+        if self.cover.is_stale() {
+            // Sixel images are part of the screen contents, only a full repaint removes them.
+            if self.cover.protocol == GraphicsProtocol::Sixel {
+                terminal.clear().map_err(AppError::terminal)?;
+                terminal
+                    .draw(|rect| self.render_frame(rect))
+                    .map_err(AppError::terminal)?;
+            }
+            self.cover.draw_graphic(terminal.backend_mut())?;
+        }
         Ok(())
     }
 
+    fn render_frame(&mut self, rect: &mut Frame<CrosstermBackend<Stdout>>) {
+        let chunks = self.layout_chunks(rect);
+        self.render_menu(rect, chunks[0]);
+        self.render_main(rect, chunks[1]);
+        self.render_message(rect, chunks[2]);
+    }
+
     fn render_menu(&self, rect: &mut Frame<CrosstermBackend<Stdout>>, area: Rect) {
         let mut menu: Vec<_> = self
             .pages
             .iter()
-            .map(|t| render_title(&t.get_title()))
+            .map(|t| render_title(t.get_title()))
             .collect();
         menu.push(render_title("Quit"));
-        let temp = self.database.data.len().to_string();
-        menu.push(render_title(temp.as_str()));
+        for (index, collection) in self.collections.iter().enumerate() {
+            let counts = match self.collection_database(index) {
+                Some(database) => {
+                    format!(" {}/{}", database.holders().len(), database.count_copies())
+                }
+                None => "".to_string(),
+            };
+            let style = match index == self.active_collection {
+                true => Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD),
+                false => Style::default().fg(Color::DarkGray),
+            };
+            menu.push(Spans::from(vec![Span::styled(
+                collection.name.clone() + &counts,
+                style,
+            )]));
+        }
 
         // let input = self.input.to_string();
         // menu.push(render_title(&input));q
@@ -76,8 +118,7 @@ impl App<'_> {
     }
 
     fn render_main(&mut self, rect: &mut Frame<CrosstermBackend<Stdout>>, area: Rect) {
-        let main_area: Rect;
-        if self.active.show_input() {
+        let main_area = if self.active.show_input() {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([Constraint::Length(3), Constraint::Min(2)].as_ref())
@@ -92,18 +133,31 @@ impl App<'_> {
                 false => Style::default(),
             };
 
+            let title = match (&self.link_record, &self.active) {
+                (Some(local), AppPages::WebSearch) => {
+                    format!(
+                        "Search a release to link \"{}\" to (Esc cancels)",
+                        local.title
+                    )
+                }
+                (_, AppPages::OnLoan) => match self.loans.selected() {
+                    Some(loan) => format!("Contact of {} (i edits, Enter saves)", loan.borrower),
+                    None => "Contact".to_string(),
+                },
+                _ => "Search".to_string(),
+            };
             self.main_input.set_block(
                 Block::default()
-                    .title("Search")
+                    .title(title)
                     .borders(Borders::ALL)
                     .border_style(border_style),
             );
             rect.render_widget(self.main_input.widget(), chunks[0]);
 
-            main_area = chunks[1];
+            chunks[1]
         } else {
-            main_area = area;
-        }
+            area
+        };
 
         self.render_main_area(rect, main_area)
     }
@@ -113,9 +167,283 @@ impl App<'_> {
             AppPages::Home => self.render_home(rect, area),
             AppPages::WebSearch => self.render_query_list(rect, area),
             AppPages::Search => self.render_search_page(rect, area),
+            AppPages::Editor => self.render_editor_page(rect, area),
+            AppPages::Browse => self.render_browse_page(rect, area),
+            AppPages::OnLoan => self.render_on_loan_page(rect, area),
+            AppPages::Analytics => self.render_analytics_page(rect, area),
         }
     }
 
+    fn render_analytics_page(&mut self, rect: &mut Frame<CrosstermBackend<Stdout>>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(4), Constraint::Min(4)].as_ref())
+            .split(area);
+
+        let stats = &self.stats;
+        let value = match (&self.valuation, &self.valuing) {
+            (_, Some(_)) => "being calculated".to_string(),
+            (Some(value), None) => format_amounts(&value.total),
+            (None, None) => "press v on the Home page".to_string(),
+        };
+        let totals = Paragraph::new(vec![
+            Spans::from(vec![
+                Span::styled(
+                    format!("{} records", stats.holders),
+                    Style::default().fg(Color::LightBlue),
+                ),
+                Span::raw(format!(
+                    "   {} copies   {} on loan",
+                    stats.copies, stats.on_loan
+                )),
+            ]),
+            Spans::from(format!("Value: {}", value)),
+        ])
+        .block(Block::default().borders(Borders::ALL).title("Totals"));
+        rect.render_widget(totals, chunks[0]);
+
+        let title = STATS_VIEWS
+            .iter()
+            .enumerate()
+            .map(|(i, v)| match i == self.stats_view {
+                true => format!("[{}]", v),
+                false => v.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!("{}  (Left/Right)", title));
+        let counts = match self.stats_view {
+            0 => &stats.by_genre,
+            1 => &stats.by_style,
+            2 => &stats.by_format,
+            3 => &stats.by_label,
+            4 => &stats.top_artists,
+            5 => &stats.by_decade,
+            _ => &stats.growth,
+        };
+
+        // Category names get wider bars and the biggest categories are shown, labels are
+        // cut to the bar width. Decades and years show the most recent ones.
+        let (bar_width, skip) = match self.stats_view {
+            0..=4 => {
+                let width = counts
+                    .iter()
+                    .map(|(k, _)| k.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .clamp(5, 12);
+                (width as u16, 0)
+            }
+            _ => {
+                let fitting = (block.inner(chunks[1]).width / 6) as usize;
+                (5, counts.len().saturating_sub(fitting))
+            }
+        };
+        let data: Vec<_> = counts
+            .iter()
+            .skip(skip)
+            .map(|(k, v)| (k.as_str(), *v))
+            .collect();
+        let chart = BarChart::default()
+            .block(block)
+            .data(&data)
+            .bar_width(bar_width)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(Color::LightBlue))
+            .value_style(Style::default().fg(Color::Black).bg(Color::LightBlue));
+        rect.render_widget(chart, chunks[1]);
+    }
+
+    fn render_on_loan_page(&mut self, rect: &mut Frame<CrosstermBackend<Stdout>>, area: Rect) {
+        let loans = List::new(
+            self.loans
+                .items
+                .iter()
+                .map(|l| {
+                    let style = match l.overdue {
+                        true => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                        false => Style::default(),
+                    };
+                    ListItem::new(Spans::from(vec![
+                        Span::styled(
+                            format!(
+                                "{:<12}",
+                                l.due.map_or("no due date".to_string(), |d| d.to_string())
+                            ),
+                            style,
+                        ),
+                        Span::styled(
+                            format!("{:<20} ", l.borrower),
+                            Style::default().fg(Color::Yellow),
+                        ),
+                        Span::raw(format!(
+                            "{} #{}  since {}",
+                            l.record.title,
+                            l.item_id,
+                            l.since.with_timezone(&Local).format("%Y-%m-%d")
+                        )),
+                        Span::styled(
+                            l.contact
+                                .as_ref()
+                                .map_or("".to_string(), |c| format!("  {}", c)),
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                    ]))
+                })
+                .collect::<Vec<_>>(),
+        )
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "On loan ({}, {} overdue)",
+            self.loans.items.len(),
+            self.loans.items.iter().filter(|l| l.overdue).count()
+        )))
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        );
+        rect.render_stateful_widget(loans, area, &mut self.loans.state);
+    }
+
+    fn render_browse_page(&mut self, rect: &mut Frame<CrosstermBackend<Stdout>>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
+            .split(area);
+
+        let containers = List::new(
+            self.locations
+                .items
+                .iter()
+                .map(|g| {
+                    ListItem::new(format!(
+                        "{}  ({})",
+                        match g.container.as_str() {
+                            "" => "Without location",
+                            container => container,
+                        },
+                        g.entries.len()
+                    ))
+                })
+                .collect::<Vec<_>>(),
+        )
+        .block(Block::default().borders(Borders::ALL).title("Locations"))
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        );
+        rect.render_stateful_widget(containers, chunks[0], &mut self.locations.state);
+
+        let entries: Vec<_> = self
+            .locations
+            .selected()
+            .map(|g| {
+                g.entries
+                    .iter()
+                    .map(|e| {
+                        ListItem::new(Spans::from(vec![
+                            Span::styled(
+                                format!("{:>6}  ", e.slot),
+                                Style::default().fg(Color::Yellow),
+                            ),
+                            Span::raw(format!(
+                                "{} - {}  #{}",
+                                e.record
+                                    .artists
+                                    .iter()
+                                    .map(|a| a.name.clone())
+                                    .collect::<Vec<_>>()
+                                    .join(", "),
+                                e.record.title,
+                                e.item_id
+                            )),
+                        ]))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let contents = List::new(entries).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Contents (Enter to search)"),
+        );
+        rect.render_widget(contents, chunks[1]);
+    }
+
+    fn render_editor_page(&mut self, rect: &mut Frame<CrosstermBackend<Stdout>>, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
+            .split(area);
+
+        let fields = List::new(
+            self.editor
+                .fields
+                .iter()
+                .map(|f| {
+                    ListItem::new(Spans::from(vec![
+                        Span::styled(f.title, Style::default().fg(Color::Yellow)),
+                        Span::raw(": "),
+                        Span::raw(f.input.lines()[0].clone()),
+                    ]))
+                })
+                .collect::<Vec<_>>(),
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(match self.editor.target {
+                    Some(_) => "Edit record",
+                    None => "New record",
+                }),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        );
+        let mut state = ListState::default();
+        state.select(Some(self.editor.selected));
+        rect.render_stateful_widget(fields, chunks[0], &mut state);
+
+        let small_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
+            .split(chunks[1]);
+
+        let border_style = match self.editor.is_input {
+            true => Style::default()
+                .remove_modifier(Modifier::UNDERLINED)
+                .fg(Color::Yellow),
+            false => Style::default(),
+        };
+        let field = self.editor.selected_field_mut();
+        let title = field.title;
+        field.input.set_cursor_line_style(Style::default());
+        field.input.set_block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(border_style),
+        );
+        rect.render_widget(field.input.widget(), small_chunks[0]);
+
+        let hint = Paragraph::new(vec![Spans::from(vec![Span::raw(
+            self.editor.selected_field().hint,
+        )])])
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Hint")
+                .border_type(BorderType::Plain),
+        );
+        rect.render_widget(hint, small_chunks[1]);
+    }
+
     fn render_home(&mut self, rect: &mut Frame<CrosstermBackend<Stdout>>, area: Rect) {
         let home = Paragraph::new(vec![
             Spans::from(vec![Span::raw("")]),
@@ -129,6 +457,7 @@ impl App<'_> {
             Spans::from(vec![Span::raw("")]),
             Spans::from(vec![Span::raw("")]),
             Spans::from(vec![Span::raw("Navigate using the keyboard.")]),
+            Spans::from(vec![Span::raw("Press v to value the collection.")]),
         ])
         .alignment(Alignment::Center)
         .block(
@@ -139,7 +468,76 @@ impl App<'_> {
                 .border_type(BorderType::Plain),
         );
 
-        rect.render_widget(home, area)
+        match &self.valuation {
+            Some(value) => {
+                let chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(11), Constraint::Min(3)].as_ref())
+                    .split(area);
+                rect.render_widget(home, chunks[0]);
+                Self::render_valuation(value, rect, chunks[1]);
+            }
+            None => rect.render_widget(home, area),
+        }
+    }
+
+    fn render_valuation(
+        value: &CollectionValue,
+        rect: &mut Frame<CrosstermBackend<Stdout>>,
+        area: Rect,
+    ) {
+        let mut lines = vec![
+            Spans::from(vec![Span::styled(
+                format!("Total: {}", format_amounts(&value.total)),
+                Style::default().fg(Color::LightBlue),
+            )]),
+            Spans::from(vec![Span::raw(format!(
+                "Paid: {}",
+                format_amounts(&value.paid)
+            ))]),
+            Spans::from(vec![Span::raw(format!(
+                "{} copies priced, {} without a price, {} failed",
+                value.priced, value.unpriced, value.failed
+            ))]),
+        ];
+        if let Some(error) = &value.error {
+            lines.push(Spans::from(vec![Span::styled(
+                error.clone(),
+                Style::default().fg(Color::Red),
+            )]));
+        }
+        // Ranked by the currency most copies are priced in, amounts in other currencies
+        // are listed next to it but never added.
+        let main = value.main_currency().unwrap_or_default();
+        for (title, totals) in [
+            ("By genre", &value.by_genre),
+            ("By format", &value.by_format),
+            ("By label", &value.by_label),
+        ] {
+            let mut totals: Vec<_> = totals.iter().collect();
+            totals.sort_by_key(|(_, amounts)| std::cmp::Reverse(amounts.get(main).copied()));
+            lines.push(Spans::from(vec![Span::raw("")]));
+            lines.push(Spans::from(vec![Span::styled(
+                title,
+                Style::default().fg(Color::Yellow),
+            )]));
+            lines.extend(totals.into_iter().take(5).map(|(name, amounts)| {
+                Spans::from(vec![Span::raw(format!(
+                    "  {}  {}",
+                    format_amounts(amounts),
+                    name
+                ))])
+            }));
+        }
+
+        let valuation = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Value")
+                .border_type(BorderType::Plain),
+        );
+        rect.render_widget(valuation, area)
     }
 
     fn render_query_list(&mut self, rect: &mut Frame<CrosstermBackend<Stdout>>, area: Rect) {
@@ -190,7 +588,7 @@ impl App<'_> {
                     };
                     self.side_input.set_block(
                         Block::default()
-                            .title("Message")
+                            .title(Self::side_input_title(&item.input))
                             .borders(Borders::ALL)
                             .border_style(border_style),
                     );
@@ -199,14 +597,29 @@ impl App<'_> {
                         .constraints([Constraint::Min(4), Constraint::Length(3)].as_ref())
                         .split(chunks[2]);
                     rect.render_widget(self.side_input.widget(), small_chunks[1]);
-                    Self::render_item_detail(&item.clone(), rect, small_chunks[0])
+                    Self::render_item_detail(item, &self.borrowers, rect, small_chunks[0])
+                }
+                None if self.is_side_input && item_holder.input.is_some() => {
+                    self.side_input.set_block(
+                        Block::default()
+                            .title(Self::side_input_title(&item_holder.input))
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(Color::Yellow)),
+                    );
+                    let small_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Min(4), Constraint::Length(3)].as_ref())
+                        .split(chunks[2]);
+                    rect.render_widget(self.side_input.widget(), small_chunks[1]);
+                    let area = self
+                        .cover
+                        .render(item_holder.record.id, rect, small_chunks[0]);
+                    Self::render_record_detail(item_holder, rect, area)
+                }
+                None => {
+                    let area = self.cover.render(item_holder.record.id, rect, chunks[2]);
+                    Self::render_record_detail(item_holder, rect, area)
                 }
-                None => Self::render_record_detail(
-                    &item_holder.record,
-                    item_holder.detail_offset,
-                    rect,
-                    chunks[2],
-                ),
             }
         }
     }
@@ -216,7 +629,7 @@ impl App<'_> {
             .search
             .list
             .selected_mut()
-            .map_or(None, |ih| ih.list.selected())
+            .and_then(|ih| ih.list.selected())
         {
             Some(_) => [
                 Constraint::Percentage(25),
@@ -258,18 +671,41 @@ impl App<'_> {
         rect.render_stateful_widget(query_list, area, &mut self.search.list.state);
     }
 
+    fn side_input_title(input: &Option<ItemInput>) -> &'static str {
+        match input {
+            Some(ItemInput::Transfer) => "Move to collection",
+            Some(ItemInput::Grade) => "Grade: <media> <sleeve>",
+            Some(ItemInput::Location) => "Move to: room/shelf/crate/slot",
+            Some(ItemInput::Lend) => "Lend to: borrower; YYYY-MM-DD; message",
+            Some(ItemInput::Acquisition) => "Purchase: YYYY-MM-DD; seller; price currency",
+            Some(ItemInput::Sale) => "Sold: price currency; buyer; message",
+            Some(ItemInput::Trade) => "Traded: counterparty; received",
+            Some(ItemInput::Event(ItemEventType::Cleaned)) => "Cleaned: message",
+            Some(ItemInput::Event(ItemEventType::Played)) => "Played: message",
+            Some(ItemInput::Event(ItemEventType::Repaired)) => "Repaired: message",
+            Some(ItemInput::Event(ItemEventType::Lost)) => "Lost: message",
+            Some(ItemInput::EditEvent) => "Edit event: YYYY-MM-DD HH:MM; type; message",
+            Some(ItemInput::Tags) => "Tags: comma separated",
+            Some(ItemInput::Field) => "Field: name = value, empty value removes it",
+            Some(ItemInput::Rating) => "Rating: 1 to 5, empty clears it",
+            Some(ItemInput::Notes) => "Review notes",
+            _ => "Message",
+        }
+    }
+
     fn render_record_detail(
-        record: &Record,
-        detail_offset: usize,
+        item_holder: &StatefulItemHolder,
         rect: &mut Frame<CrosstermBackend<Stdout>>,
         area: Rect,
     ) {
-        let spans: Vec<_> = record
-            .get_lines()
+        let lines = item_holder.get_lines();
+        // The record lines can shrink below the scroll offset, e.g. after removing tags.
+        let detail_offset = item_holder.detail_offset.min(lines.len());
+        let spans: Vec<_> = lines
             .into_iter()
+            .skip(detail_offset)
             .map(|s| Spans::from(vec![Span::raw(""), Span::raw(s)]))
-            .collect::<Vec<_>>()[detail_offset..]
-            .into();
+            .collect();
 
         let detail = Paragraph::new(spans).wrap(Wrap { trim: false }).block(
             Block::default()
@@ -283,25 +719,63 @@ impl App<'_> {
     }
 
     fn render_item_detail(
-        stateful_item: &StatefulItem,
+        stateful_item: &mut StatefulItem,
+        borrowers: &Borrowers,
         rect: &mut Frame<CrosstermBackend<Stdout>>,
         area: Rect,
     ) {
-        let spans: Vec<_> = stateful_item
+        let mut spans: Vec<_> = stateful_item
             .item
-            .events
-            .iter()
-            .map(|ie| Spans::from(vec![Span::raw("\n    "), Span::raw(format!("{:#?}", ie))]))
-            .collect::<Vec<_>>()
-            .into();
+            .annotations
+            .get_lines()
+            .into_iter()
+            .map(Spans::from)
+            .collect();
+        if !spans.is_empty() {
+            spans.push(Spans::from(""));
+        }
+        let mut selected_line = None;
+        for (index, event) in stateful_item.item.events.iter().enumerate().rev() {
+            let mut event_spans = event_spans(event, borrowers);
+            if stateful_item.selected_event == Some(index) {
+                selected_line = Some(spans.len());
+                if let Some(first) = event_spans.first_mut() {
+                    for span in first.0.iter_mut() {
+                        span.style = span.style.bg(Color::DarkGray);
+                    }
+                }
+            }
+            spans.extend(event_spans);
+        }
 
-        let detail = Paragraph::new(spans).wrap(Wrap { trim: false }).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
-                .title("Item events")
-                .border_type(BorderType::Plain),
-        );
+        // Keep the selected event on screen, wrapped lines aren't accounted for.
+        if let Some(line) = selected_line {
+            let height = area.height.saturating_sub(3) as usize;
+            if line < stateful_item.detail_offset {
+                stateful_item.detail_offset = line;
+            } else if line > stateful_item.detail_offset + height {
+                stateful_item.detail_offset = line - height;
+            }
+        }
+
+        let title = match stateful_item.item.audit.len() {
+            0 => format!("Item events ({})", stateful_item.item.state()),
+            edits => format!(
+                "Item events ({}, {} edits)",
+                stateful_item.item.state(),
+                edits
+            ),
+        };
+        let detail = Paragraph::new(spans)
+            .wrap(Wrap { trim: false })
+            .scroll((stateful_item.detail_offset as u16, 0))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title(title)
+                    .border_type(BorderType::Plain),
+            );
         rect.render_widget(detail, area);
     }
 
@@ -314,7 +788,23 @@ impl App<'_> {
             item_holder_list
                 .items
                 .iter()
-                .map(|i| ListItem::new(format!("{}", i.item.id)))
+                .map(|i| {
+                    let state = match i.item.state() {
+                        ItemState::InCollection => "".to_string(),
+                        state => format!("  [{}]", state),
+                    };
+                    ListItem::new(format!(
+                        "{}  {}/{}  {}{}",
+                        i.item.id,
+                        grade_code(i.item.media_condition),
+                        grade_code(i.item.sleeve_condition),
+                        i.item
+                            .location
+                            .as_ref()
+                            .map_or("".to_string(), |l| l.to_string()),
+                        state
+                    ))
+                })
                 .collect::<Vec<_>>(),
         )
         .block(Block::default().borders(Borders::ALL).title("List"))
@@ -341,7 +831,7 @@ impl App<'_> {
     }
 }
 
-fn render_title(title: &str) -> Spans {
+fn render_title(title: &str) -> Spans<'_> {
     let (first, rest) = title.split_at(1);
 
     Spans::from(vec![
@@ -1,24 +1,58 @@
-use super::super::models::error::Result;
+use record_catalog::models::error::{AppError, Result};
 
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
-use std::io::Stdout;
+use crossterm::{
+    cursor::Show,
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    io::Stdout,
+    ops::{Deref, DerefMut},
+};
 use tui::backend::CrosstermBackend;
 use tui::Terminal;
 
-pub fn start() -> Result<Terminal<CrosstermBackend<Stdout>>> {
-    enable_raw_mode().expect("can run in raw mode");
+/// The terminal in raw mode on the alternate screen, put back to normal when dropped,
+/// whether the app quits, returns an error or panics.
+pub struct TerminalGuard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl Deref for TerminalGuard {
+    type Target = Terminal<CrosstermBackend<Stdout>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.terminal
+    }
+}
+
+impl DerefMut for TerminalGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.terminal
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
 
-    let stdout = std::io::stdout();
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    terminal.clear()?;
+pub fn start() -> Result<TerminalGuard> {
+    let backend = CrosstermBackend::new(std::io::stdout());
+    let mut guard = TerminalGuard {
+        terminal: Terminal::new(backend).map_err(AppError::terminal)?,
+    };
+    enable_raw_mode().map_err(AppError::terminal)?;
+    execute!(guard.backend_mut(), EnterAlternateScreen).map_err(AppError::terminal)?;
+    guard.clear().map_err(AppError::terminal)?;
 
-    Ok(terminal)
+    Ok(guard)
 }
 
-pub fn end(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
-    disable_raw_mode()?;
-    terminal.clear()?;
-    terminal.show_cursor()?;
-    Ok(())
+/// Leaves raw mode and the alternate screen. Safe to call more than once, errors are
+/// ignored since this also runs while panicking.
+pub fn restore() {
+    let _ = disable_raw_mode();
+    let _ = execute!(std::io::stdout(), LeaveAlternateScreen, Show);
 }
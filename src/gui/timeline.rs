@@ -0,0 +1,145 @@
+use chrono::Local;
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+};
+
+use record_catalog::{
+    borrowers::Borrowers,
+    models::item_holder::{EventPayload, ItemEvent, ItemEventType},
+    valuation::format_cents,
+};
+
+fn icon(event_type: ItemEventType) -> &'static str {
+    match event_type {
+        ItemEventType::Created => "✚",
+        ItemEventType::Message => "✎",
+        ItemEventType::Lent => "→",
+        ItemEventType::Returned => "←",
+        ItemEventType::Graded => "★",
+        ItemEventType::Moved => "⇄",
+        ItemEventType::TransferredIn => "⇲",
+        ItemEventType::TransferredOut => "⇱",
+        ItemEventType::Sold => "$",
+        ItemEventType::Traded => "⇆",
+        ItemEventType::Cleaned => "✧",
+        ItemEventType::Played => "♪",
+        ItemEventType::Repaired => "⚒",
+        ItemEventType::Lost => "✗",
+    }
+}
+
+fn color(event_type: ItemEventType) -> Color {
+    match event_type {
+        ItemEventType::Created | ItemEventType::TransferredIn => Color::Green,
+        ItemEventType::Lent | ItemEventType::Returned => Color::Yellow,
+        ItemEventType::Graded | ItemEventType::Cleaned | ItemEventType::Repaired => {
+            Color::LightBlue
+        }
+        ItemEventType::Played => Color::Magenta,
+        ItemEventType::Moved => Color::Cyan,
+        ItemEventType::Sold | ItemEventType::Traded | ItemEventType::TransferredOut => {
+            Color::LightRed
+        }
+        ItemEventType::Lost => Color::Red,
+        ItemEventType::Message => Color::White,
+    }
+}
+
+/// Header line with the local date followed by the indented payload and message.
+pub fn event_spans(event: &ItemEvent, borrowers: &Borrowers) -> Vec<Spans<'static>> {
+    let color = color(event.event_type);
+    let mut spans = vec![Spans::from(vec![
+        Span::styled(
+            format!("{} ", icon(event.event_type)),
+            Style::default().fg(color),
+        ),
+        Span::styled(
+            event
+                .date
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M  ")
+                .to_string(),
+            Style::default().fg(Color::DarkGray),
+        ),
+        Span::styled(
+            event.event_type.label(),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ),
+    ])];
+
+    let details = match &event.payload {
+        Some(EventPayload::Loan { borrower_id, due }) => Some(format!(
+            "To {}, due {}",
+            borrowers.name(Some(*borrower_id)),
+            due
+        )),
+        Some(EventPayload::Sale {
+            price,
+            currency,
+            buyer,
+        }) => Some(match buyer.as_str() {
+            "" => format_cents(*price, currency),
+            buyer => format!("{} to {}", format_cents(*price, currency), buyer),
+        }),
+        Some(EventPayload::Trade {
+            counterparty,
+            received,
+        }) => Some(format!("With {} for {}", counterparty, received)),
+        None => None,
+    };
+    for text in details.iter().chain(event.message.iter()) {
+        spans.push(Spans::from(Span::raw(format!("  {}", text))));
+    }
+    spans.push(Spans::from(""));
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(event: &ItemEvent, borrowers: &Borrowers) -> Vec<String> {
+        event_spans(event, borrowers)
+            .iter()
+            .skip(1)
+            .map(|spans| spans.0.iter().map(|s| s.content.as_ref()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn payloads_are_shown_before_the_message() {
+        let path = std::env::temp_dir().join(format!(
+            "timeline-test-borrowers-{}.json",
+            std::process::id()
+        ));
+        let mut borrowers = Borrowers::new(path.to_str().unwrap()).unwrap();
+        let bob = borrowers.find_or_add("Bob").unwrap();
+
+        let sale = ItemEvent::sale("12.5 eur; Bob; Record fair").unwrap();
+        assert_eq!(
+            lines(&sale, &borrowers),
+            vec!["  12.50 EUR to Bob", "  Record fair", ""]
+        );
+        let sale = ItemEvent::sale("3 USD").unwrap();
+        assert_eq!(lines(&sale, &borrowers), vec!["  3.00 USD", ""]);
+        let trade = ItemEvent::trade("Alice; Low").unwrap();
+        assert_eq!(lines(&trade, &borrowers), vec!["  With Alice for Low", ""]);
+
+        let due = chrono::NaiveDate::from_ymd_opt(2030, 5, 1).unwrap();
+        let loan = ItemEvent::with_payload(
+            ItemEventType::Lent,
+            None,
+            EventPayload::Loan {
+                borrower_id: bob,
+                due,
+            },
+        );
+        assert_eq!(
+            lines(&loan, &borrowers),
+            vec!["  To Bob, due 2030-05-01", ""]
+        );
+        let note = ItemEvent::with_message(ItemEventType::Message, "Nice".to_string());
+        assert_eq!(lines(&note, &borrowers), vec!["  Nice", ""]);
+    }
+}
@@ -5,13 +5,22 @@ use std::{
 };
 
 use crossterm::event::{self, Event, KeyCode};
+use tui_textarea::TextArea;
 
-use crate::models::{
-    app::{App, AppPage, AppPages, Navigation},
-    error::Result,
-    item_holder::ItemEvent,
+use record_catalog::models::error::{AppError, Result};
+
+use crate::{
+    crash,
+    state::{
+        app::{App, AppPage, AppPages, Navigation, STATS_VIEWS},
+        list::StatefulList,
+        stateful::ItemInput,
+    },
 };
 
+/// Saves the text of the side input, see [`App::submit_side_input`].
+type SideInputAction<'a> = fn(&mut App<'a>, &str) -> Result<()>;
+
 pub enum CustomEvent<I> {
     Input(I),
     Tick,
@@ -28,26 +37,113 @@ pub fn start() -> mpsc::Receiver<CustomEvent<event::KeyEvent>> {
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_secs(0));
 
-            if event::poll(timeout).expect("poll works") {
-                if let Event::Key(key) = event::read().expect("can read events") {
-                    tx.send(CustomEvent::Input(key)).expect("can send events");
+            match read_key(timeout) {
+                Ok(Some(key)) => {
+                    if tx.send(CustomEvent::Input(key)).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => {}
+                // Ends the thread, the app stops once it finds the channel closed.
+                Err(e) => {
+                    crash::record(format!("Reading the keyboard failed: {}", e));
+                    break;
                 }
             }
 
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(CustomEvent::Tick) {
-                    last_tick = Instant::now();
-                }
+            if last_tick.elapsed() >= tick_rate && tx.send(CustomEvent::Tick).is_ok() {
+                last_tick = Instant::now();
             }
         }
     });
     rx
 }
 
+/// Waits up to `timeout` for a key press.
+fn read_key(timeout: Duration) -> std::io::Result<Option<event::KeyEvent>> {
+    if !event::poll(timeout)? {
+        return Ok(None);
+    }
+    match event::read()? {
+        Event::Key(key) => Ok(Some(key)),
+        _ => Ok(None),
+    }
+}
+
 impl App<'_> {
-    pub fn input(&mut self, event: CustomEvent<event::KeyEvent>) -> Result<bool> {
-        let navigation = self.handle_input(event)?;
-        self.navigation(navigation)
+    /// Handles a key press or tick, returns whether the app should quit. Errors end up
+    /// in the message box so a failed action never takes the app down.
+    pub fn input(&mut self, event: CustomEvent<event::KeyEvent>) -> bool {
+        if let CustomEvent::Input(key) = &event {
+            let key = match key.modifiers.is_empty() {
+                true => format!("{:?}", key.code),
+                false => format!("{:?}+{:?}", key.modifiers, key.code),
+            };
+            crash::record(format!("{} on {}", key, self.active.get_title()));
+        }
+        let result = match event {
+            CustomEvent::Tick => {
+                self.poll_valuation();
+                self.watch_database()
+            }
+            _ => Ok(()),
+        }
+        .and_then(|_| self.handle_input(event))
+        .and_then(|navigation| self.navigation(navigation));
+        match result {
+            Ok(quit) => quit,
+            Err(AppError::ExternalChange(_)) => {
+                self.prompt_external_change();
+                false
+            }
+            Err(e) => {
+                crash::record(format!("Error: {}", e));
+                self.message_box = e.to_string();
+                false
+            }
+        }
+    }
+
+    fn watch_database(&mut self) -> Result<()> {
+        if !self.watch_database
+            || self.external_change
+            || self.last_watch.elapsed() < Duration::from_secs(2)
+        {
+            return Ok(());
+        }
+        self.last_watch = Instant::now();
+        if self.database.has_external_changes()? {
+            self.prompt_external_change();
+        }
+        Ok(())
+    }
+
+    fn prompt_external_change(&mut self) {
+        self.external_change = true;
+        self.message_box =
+            "Database changed on disk: (r)eload, (m)erge, (o)verwrite or (Esc) ignore".to_string();
+    }
+
+    fn handle_external_change_input(&mut self, code: KeyCode) -> Result<()> {
+        match code {
+            KeyCode::Char('r') => {
+                self.database.reload()?;
+                self.message_box = "Database reloaded".to_string();
+            }
+            KeyCode::Char('m') => {
+                let report = self.database.merge_external()?;
+                self.message_box = format!("Changes merged, {} conflicts", report.conflicts.len());
+            }
+            KeyCode::Char('o') => {
+                self.database.overwrite()?;
+                self.message_box = "Database overwritten".to_string();
+            }
+            KeyCode::Esc => self.message_box = "".to_string(),
+            _ => return Ok(()),
+        }
+        self.external_change = false;
+        self.search.list = StatefulList::with_items(vec![]);
+        Ok(())
     }
 
     fn navigation(&mut self, navigation: Navigation) -> Result<bool> {
@@ -58,15 +154,28 @@ impl App<'_> {
 
         match navigation {
             Navigation::NavigatePage(page) => {
+                // Linking only carries on to the Web search page.
+                if page != AppPages::WebSearch {
+                    self.link_record = None;
+                }
+                self.load_page(&page);
                 self.active = page;
             }
+            Navigation::NextCollection => {
+                self.link_record = None;
+                self.next_collection()?
+            }
             Navigation::EnterInput => {
+                if self.active == AppPages::OnLoan {
+                    let contact = self.loans.selected().and_then(|l| l.contact.clone());
+                    self.main_input = TextArea::from([contact.unwrap_or_default()]);
+                }
                 if self.active.show_input() {
                     self.is_main_input = true;
                 }
             }
             Navigation::EnterSideInput => {
-                if self.search.is_item_selected() {
+                if self.search.selected_input().is_some() {
                     self.is_side_input = true
                 }
             }
@@ -74,31 +183,26 @@ impl App<'_> {
             Navigation::QuitSideInput => self.is_side_input = false,
             Navigation::Quit => return Ok(true),
             Navigation::InputSubmit => match self.active {
-                AppPages::Search => self.search(None)?,
-                AppPages::WebSearch => self.web_search()?,
-                _ => {}
-            },
-            Navigation::SideInputSubmit => match self.active {
                 AppPages::Search => {
-                    if let Some(holder) = self.search.list.selected_mut() {
-                        if let Some(stateful_item) = holder.list.selected_mut() {
-                            stateful_item.item.events.push(ItemEvent::with_message(
-                                stateful_item.clone().input.unwrap(),
-                                self.side_input.lines()[0].clone(),
-                            ));
-                            self.database
-                                .update_item(&holder.record, stateful_item.item.clone())?;
-                        }
-                    }
+                    self.link_record = None;
+                    self.search(None)?
                 }
+                AppPages::WebSearch => self.web_search()?,
+                AppPages::OnLoan => self.set_loan_contact()?,
                 _ => {}
             },
+            Navigation::SideInputSubmit => {
+                if let Some(input) = self.search.selected_input() {
+                    let text = self.side_input.lines()[0].clone();
+                    self.submit_side_input(input, &text)?;
+                }
+            }
             Navigation::Combined(vector) => {
-                let results: Vec<_> = vector
-                    .into_iter()
-                    .map(|n| self.navigation(n).unwrap())
-                    .collect();
-                return Ok(results.contains(&true));
+                let mut quit = false;
+                for navigation in vector {
+                    quit |= self.navigation(navigation)?;
+                }
+                return Ok(quit);
             }
             Navigation::DoNotihing => {}
         }
@@ -106,10 +210,50 @@ impl App<'_> {
         Ok(false)
     }
 
+    /// Applies the side input to the selected record or copy and confirms it in the
+    /// message box. Errors go back to [`App::input`] like those of any other action.
+    fn submit_side_input(&mut self, input: ItemInput, text: &str) -> Result<()> {
+        let (submit, done): (SideInputAction<'_>, &str) = match input {
+            ItemInput::Transfer => (Self::transfer_selected_item, "Item moved"),
+            ItemInput::Grade => (Self::grade_selected_item, "Item graded"),
+            ItemInput::Lend => (Self::lend_selected_item, "Item lent"),
+            ItemInput::Location => (Self::move_selected_item, "Item moved"),
+            ItemInput::Acquisition => (Self::set_selected_item_acquisition, "Purchase saved"),
+            ItemInput::Sale => (Self::sell_selected_item, "Item sold"),
+            ItemInput::Trade => (Self::trade_selected_item, "Item traded"),
+            ItemInput::Tags => (Self::tag_selected, "Tags saved"),
+            ItemInput::Field => (Self::set_selected_field, "Field saved"),
+            ItemInput::Rating => (Self::rate_selected, "Rating saved"),
+            ItemInput::Notes => (Self::set_selected_notes, "Notes saved"),
+            ItemInput::EditEvent => (Self::edit_selected_event, "Event updated"),
+            ItemInput::Event(event_type) if self.active == AppPages::Search => {
+                return self.add_selected_item_event(event_type, text)
+            }
+            ItemInput::Event(_) => return Ok(()),
+        };
+        submit(self, text)?;
+        self.message_box = done.to_string();
+        Ok(())
+    }
+
     fn handle_input(&mut self, event: CustomEvent<event::KeyEvent>) -> Result<Navigation> {
+        if self.external_change {
+            if let CustomEvent::Input(key_event) = event {
+                self.handle_external_change_input(key_event.code)?;
+            }
+            return Ok(Navigation::DoNotihing);
+        };
+
+        if self.editor.is_input {
+            if let CustomEvent::Input(key_event) = event {
+                return Ok(self.handle_editor_field_input(key_event));
+            }
+            return Ok(Navigation::DoNotihing);
+        };
+
         if self.is_main_input {
-            match event {
-                CustomEvent::Input(key_event) => match key_event.code {
+            if let CustomEvent::Input(key_event) = event {
+                match key_event.code {
                     KeyCode::Esc => return Ok(Navigation::QuitInput),
                     KeyCode::Enter => {
                         return Ok(Navigation::Combined(vec![
@@ -120,16 +264,15 @@ impl App<'_> {
                     _ => {
                         self.main_input.input(key_event);
                     }
-                },
-                _ => {}
+                }
             };
 
             return Ok(Navigation::DoNotihing);
         };
 
         if self.is_side_input {
-            match event {
-                CustomEvent::Input(key_event) => match key_event.code {
+            if let CustomEvent::Input(key_event) = event {
+                match key_event.code {
                     KeyCode::Esc => return Ok(Navigation::QuitSideInput),
                     KeyCode::Enter => {
                         return Ok(Navigation::Combined(vec![
@@ -140,8 +283,7 @@ impl App<'_> {
                     _ => {
                         self.side_input.input(key_event);
                     }
-                },
-                _ => {}
+                }
             };
 
             return Ok(Navigation::DoNotihing);
@@ -152,6 +294,11 @@ impl App<'_> {
                 KeyCode::Char('h') => Navigation::NavigatePage(AppPages::Home),
                 KeyCode::Char('w') => Navigation::NavigatePage(AppPages::WebSearch),
                 KeyCode::Char('s') => Navigation::NavigatePage(AppPages::Search),
+                KeyCode::Char('e') => Navigation::NavigatePage(AppPages::Editor),
+                KeyCode::Char('c') => Navigation::NextCollection,
+                KeyCode::Char('b') => Navigation::NavigatePage(AppPages::Browse),
+                KeyCode::Char('o') => Navigation::NavigatePage(AppPages::OnLoan),
+                KeyCode::Char('a') => Navigation::NavigatePage(AppPages::Analytics),
                 KeyCode::Char('i') => Navigation::EnterInput,
                 KeyCode::Char('q') => Navigation::Quit,
 
@@ -164,10 +311,79 @@ impl App<'_> {
 
     pub fn handle_page_specific_input(&mut self, code: KeyCode) -> Result<Navigation> {
         match self.active {
-            AppPages::Home => Ok(Navigation::DoNotihing),
+            AppPages::Home => self.handle_home_input(code),
             AppPages::Search => self.handle_search_input(code),
             AppPages::WebSearch => self.handle_web_search_input(code),
+            AppPages::Editor => self.handle_editor_input(code),
+            AppPages::Browse => self.handle_browse_input(code),
+            AppPages::OnLoan => self.handle_on_loan_input(code),
+            AppPages::Analytics => self.handle_analytics_input(code),
+        }
+    }
+
+    pub fn handle_analytics_input(&mut self, code: KeyCode) -> Result<Navigation> {
+        match code {
+            KeyCode::Right => self.stats_view = (self.stats_view + 1) % STATS_VIEWS.len(),
+            KeyCode::Left => {
+                self.stats_view = (self.stats_view + STATS_VIEWS.len() - 1) % STATS_VIEWS.len()
+            }
+            _ => {}
+        }
+        Ok(Navigation::DoNotihing)
+    }
+
+    pub fn handle_home_input(&mut self, code: KeyCode) -> Result<Navigation> {
+        if let KeyCode::Char('v') = code {
+            if let Err(e) = self.value_collection() {
+                self.message_box = format!("Couldn't value the collection: {}", e);
+            }
         }
+        Ok(Navigation::DoNotihing)
+    }
+
+    pub fn handle_browse_input(&mut self, code: KeyCode) -> Result<Navigation> {
+        Ok(match code {
+            KeyCode::Up => {
+                self.locations.previous();
+                Navigation::DoNotihing
+            }
+            KeyCode::Down => {
+                self.locations.next();
+                Navigation::DoNotihing
+            }
+            KeyCode::Enter => match self.locations.selected() {
+                Some(group) if !group.container.is_empty() => {
+                    self.main_input = TextArea::from([format!("loc:\"{}\"", group.container)]);
+                    Navigation::Combined(vec![
+                        Navigation::NavigatePage(AppPages::Search),
+                        Navigation::InputSubmit,
+                    ])
+                }
+                _ => Navigation::DoNotihing,
+            },
+            _ => Navigation::DoNotihing,
+        })
+    }
+
+    pub fn handle_on_loan_input(&mut self, code: KeyCode) -> Result<Navigation> {
+        Ok(match code {
+            KeyCode::Up => {
+                self.loans.previous();
+                Navigation::DoNotihing
+            }
+            KeyCode::Down => {
+                self.loans.next();
+                Navigation::DoNotihing
+            }
+            KeyCode::Enter => match self.loans.selected() {
+                Some(loan) => {
+                    self.search(Some(loan.record.clone()))?;
+                    Navigation::NavigatePage(AppPages::Search)
+                }
+                None => Navigation::DoNotihing,
+            },
+            _ => Navigation::DoNotihing,
+        })
     }
 
     pub fn handle_web_search_input(&mut self, code: KeyCode) -> Result<Navigation> {
@@ -184,17 +400,28 @@ impl App<'_> {
             }
             KeyCode::Enter => match self.select_release_from_web_search() {
                 Ok(r) => {
+                    let r = match &self.link_record {
+                        Some(local) => self.database.link(local, r)?.record,
+                        None => r,
+                    };
+                    self.link_record = None;
                     self.search(Some(r))?;
                     Navigation::Combined(vec![
                         Navigation::NavigatePage(AppPages::Search),
                         // Navigation::InputSubmit,
                     ])
                 }
-                Err(_) => {
-                    self.message_box = "Release couldn't be loaded!".to_string();
+                Err(e) => {
+                    self.message_box = e.to_string();
                     Navigation::DoNotihing
                 }
             },
+            KeyCode::Esc => {
+                if self.link_record.take().is_some() {
+                    self.message_box = "Linking cancelled".to_string();
+                }
+                Navigation::DoNotihing
+            }
 
             _ => Navigation::DoNotihing,
         })
@@ -1,2 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod inputer;
+pub mod page_editor;
 pub mod page_search;
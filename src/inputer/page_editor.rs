@@ -0,0 +1,78 @@
+use crossterm::event::{KeyCode, KeyEvent};
+
+use record_catalog::models::{error::Result, record::Record};
+
+use crate::state::{
+    app::{App, AppPages, Navigation},
+    editor::Editor,
+};
+
+impl App<'_> {
+    pub fn handle_editor_field_input(&mut self, key_event: KeyEvent) -> Navigation {
+        let field = self.editor.selected_field_mut();
+        match key_event.code {
+            KeyCode::Esc => self.editor.is_input = false,
+            KeyCode::Enter if !field.multiline => self.editor.is_input = false,
+            _ => {
+                field.input.input(key_event);
+            }
+        }
+        Navigation::DoNotihing
+    }
+
+    pub fn handle_editor_input(&mut self, code: KeyCode) -> Result<Navigation> {
+        Ok(match code {
+            KeyCode::Up => {
+                self.editor.previous();
+                Navigation::DoNotihing
+            }
+            KeyCode::Down => {
+                self.editor.next();
+                Navigation::DoNotihing
+            }
+            KeyCode::Enter => {
+                self.editor.is_input = true;
+                Navigation::DoNotihing
+            }
+            KeyCode::Char('r') => {
+                self.editor = match &self.editor.target {
+                    Some(record) => Editor::from_record(record),
+                    None => Editor::empty(),
+                };
+                self.message_box = "Form reset".to_string();
+                Navigation::DoNotihing
+            }
+            KeyCode::Char('n') => {
+                self.editor = Editor::empty();
+                self.message_box = "New record".to_string();
+                Navigation::DoNotihing
+            }
+            KeyCode::Char('+') => {
+                let base = match &self.editor.target {
+                    Some(record) => record.clone(),
+                    None => Record {
+                        id: self.database.next_local_id(),
+                        ..Default::default()
+                    },
+                };
+                let record = match self.editor.to_record(&base) {
+                    Ok(record) => record,
+                    Err(e) => {
+                        self.message_box = e.to_string();
+                        return Ok(Navigation::DoNotihing);
+                    }
+                };
+                match &self.editor.target {
+                    Some(original) => self.database.update_record(original, record.clone())?,
+                    None => {
+                        self.database.add(record.clone())?;
+                    }
+                }
+                self.editor = Editor::empty();
+                self.search(Some(record))?;
+                Navigation::NavigatePage(AppPages::Search)
+            }
+            _ => Navigation::DoNotihing,
+        })
+    }
+}
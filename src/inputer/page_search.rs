@@ -1,17 +1,52 @@
 use crossterm::event::KeyCode;
+use tui_textarea::TextArea;
 
-use crate::models::{
-    app::{App, Navigation},
-    error::Result,
-    item_holder::ItemEventType,
+use record_catalog::models::{error::Result, item_holder::ItemEventType};
+
+use crate::state::{
+    app::{App, AppPages, Navigation},
+    editor::Editor,
+    stateful::{ItemInput, StatefulItemHolder},
 };
 
 impl App<'_> {
     pub fn handle_search_input(&mut self, code: KeyCode) -> Result<Navigation> {
         Ok(match code {
+            KeyCode::Up | KeyCode::Down
+                if self
+                    .search
+                    .get_selected_item_mut()
+                    .is_some_and(|i| i.selected_event.is_some()) =>
+            {
+                if let Some(item) = self.search.get_selected_item_mut() {
+                    // The timeline shows the newest event first.
+                    let last = item.item.events.len().saturating_sub(1);
+                    item.selected_event = item.selected_event.map(|e| match code {
+                        KeyCode::Up => (e + 1).min(last),
+                        _ => e.saturating_sub(1),
+                    });
+                }
+                Navigation::DoNotihing
+            }
+            KeyCode::Tab => {
+                if let Some(item) = self.search.get_selected_item_mut() {
+                    item.selected_event = match item.selected_event {
+                        Some(_) => None,
+                        None => item.item.events.len().checked_sub(1),
+                    };
+                }
+                Navigation::QuitSideInput
+            }
+            KeyCode::Delete => {
+                if self.search.is_item_selected() {
+                    self.delete_selected_event()?;
+                    self.message_box = "Event deleted".to_string();
+                }
+                Navigation::DoNotihing
+            }
             KeyCode::Up => {
                 match self.search.list.selected_mut() {
-                    Some(item_holder) if matches!(item_holder.list.selected(), Some(_)) => {
+                    Some(item_holder) if item_holder.list.selected().is_some() => {
                         item_holder.list.previous();
                         return Ok(Navigation::QuitSideInput);
                     }
@@ -21,7 +56,7 @@ impl App<'_> {
             }
             KeyCode::Down => {
                 match self.search.list.selected_mut() {
-                    Some(item_holder) if matches!(item_holder.list.selected(), Some(_)) => {
+                    Some(item_holder) if item_holder.list.selected().is_some() => {
                         item_holder.list.next();
                         return Ok(Navigation::QuitSideInput);
                     }
@@ -30,47 +65,46 @@ impl App<'_> {
                 Navigation::DoNotihing
             }
             KeyCode::Right => {
-                match &mut self.search.list.selected_mut() {
-                    Some(selected) => selected.list.next(),
-                    None => (),
+                if let Some(selected) = &mut self.search.list.selected_mut() {
+                    selected.list.next()
                 }
                 Navigation::DoNotihing
             }
             KeyCode::Left => {
-                match &mut self.search.list.selected_mut() {
-                    Some(selected) => selected.list.unselect(),
-                    None => (),
+                if let Some(selected) = &mut self.search.list.selected_mut() {
+                    match selected.list.selected_mut() {
+                        Some(item) if item.selected_event.is_some() => item.selected_event = None,
+                        _ => selected.list.unselect(),
+                    }
                 }
                 Navigation::DoNotihing
             }
             KeyCode::Char('+') => {
-                match &mut self.search.list.selected_mut() {
-                    Some(r) => {
-                        let mut updated = self.database.add(r.record.clone())?.to_stateful();
-                        updated.list.previous();
-                        updated.list.previous();
-                        self.message_box = "Record Saved".to_string();
-                        self.search.list.update_selected(updated);
-                        return Ok(Navigation::DoNotihing);
-                    }
-                    None => {}
+                if let Some(r) = &mut self.search.list.selected_mut() {
+                    let mut updated =
+                        StatefulItemHolder::from(self.database.add(r.record.clone())?);
+                    updated.list.previous();
+                    updated.list.previous();
+                    self.message_box = "Record Saved".to_string();
+                    self.search.list.update_selected(updated);
+                    return Ok(Navigation::DoNotihing);
                 }
 
                 Navigation::DoNotihing
             }
             KeyCode::Char('_') => {
-                match self.search.list.selected_mut() {
-                    Some(selected) if matches!(selected.list.selected(), Some(_)) => {
-                        let index = selected.list.state.selected().unwrap();
-                        match self.database.remove_holder_item(&selected.record, index) {
-                            Ok(_) => {
-                                selected.list.remove_at_index(index);
-                                self.message_box = "Item removed".to_string();
-                            }
-                            Err(_) => self.message_box = "Couldn't remove item".to_string(),
-                        }
+                let selected = self
+                    .search
+                    .list
+                    .selected_mut()
+                    .and_then(|s| Some((s.list.state.selected()?, s)));
+                match selected {
+                    Some((index, selected)) => {
+                        self.database.remove_holder_item(&selected.record, index)?;
+                        selected.list.remove_at_index(index);
+                        self.message_box = "Item removed".to_string();
                     }
-                    _ => self.message_box = "Item not selected".to_string(),
+                    None => self.message_box = "Item not selected".to_string(),
                 }
                 Navigation::DoNotihing
             }
@@ -89,7 +123,9 @@ impl App<'_> {
                 Navigation::DoNotihing
             }
             KeyCode::PageUp => {
-                if let Some(mut selected) = self.search.list.selected_mut() {
+                if let Some(item) = self.search.get_selected_item_mut() {
+                    item.detail_offset = item.detail_offset.saturating_sub(1);
+                } else if let Some(selected) = self.search.list.selected_mut() {
                     if selected.detail_offset > 0 {
                         selected.detail_offset -= 1;
                     }
@@ -97,8 +133,13 @@ impl App<'_> {
                 Navigation::DoNotihing
             }
             KeyCode::PageDown => {
-                if let Some(mut selected) = self.search.list.selected_mut() {
-                    if selected.detail_offset < selected.record.get_lines().len() - 1 {
+                if let Some(item) = self.search.get_selected_item_mut() {
+                    // Every event takes at least its header and a blank line.
+                    if item.detail_offset + 1 < item.item.events.len() * 2 {
+                        item.detail_offset += 1;
+                    }
+                } else if let Some(selected) = self.search.list.selected_mut() {
+                    if selected.detail_offset < selected.get_lines().len() - 1 {
                         selected.detail_offset += 1;
                     }
                 }
@@ -107,7 +148,39 @@ impl App<'_> {
             }
             KeyCode::Char('m') => {
                 if let Some(item) = &mut self.search.get_selected_item_mut() {
-                    item.input = Some(ItemEventType::Message);
+                    item.input = Some(ItemInput::Event(ItemEventType::Message));
+                    Navigation::EnterSideInput
+                } else {
+                    Navigation::DoNotihing
+                }
+            }
+            KeyCode::Char('g') => {
+                if let Some(item) = &mut self.search.get_selected_item_mut() {
+                    item.input = Some(ItemInput::Grade);
+                    Navigation::EnterSideInput
+                } else {
+                    Navigation::DoNotihing
+                }
+            }
+            KeyCode::Char('L') => {
+                if let Some(item) = &mut self.search.get_selected_item_mut() {
+                    item.input = Some(ItemInput::Location);
+                    Navigation::EnterSideInput
+                } else {
+                    Navigation::DoNotihing
+                }
+            }
+            KeyCode::Char('p') => {
+                if let Some(item) = &mut self.search.get_selected_item_mut() {
+                    item.input = Some(ItemInput::Acquisition);
+                    Navigation::EnterSideInput
+                } else {
+                    Navigation::DoNotihing
+                }
+            }
+            KeyCode::Char('T') => {
+                if let Some(item) = &mut self.search.get_selected_item_mut() {
+                    item.input = Some(ItemInput::Transfer);
                     Navigation::EnterSideInput
                 } else {
                     Navigation::DoNotihing
@@ -115,13 +188,123 @@ impl App<'_> {
             }
             KeyCode::Char('l') => {
                 if let Some(item) = &mut self.search.get_selected_item_mut() {
-                    item.input = Some(ItemEventType::Lent);
+                    item.input = Some(ItemInput::Lend);
+                    Navigation::EnterSideInput
+                } else {
+                    Navigation::DoNotihing
+                }
+            }
+            KeyCode::Char(c @ ('#' | 'f' | '*' | 'N')) => match self.search.list.selected_mut() {
+                Some(holder) => {
+                    // Rating and notes always belong to the record.
+                    let (input, annotations) = match holder.list.selected_mut() {
+                        Some(item) => (&mut item.input, &item.item.annotations),
+                        None => (&mut holder.input, &holder.annotations),
+                    };
+                    let text = match c {
+                        '#' => {
+                            *input = Some(ItemInput::Tags);
+                            annotations.tags.join(", ")
+                        }
+                        'f' => {
+                            *input = Some(ItemInput::Field);
+                            "".to_string()
+                        }
+                        '*' => {
+                            *input = Some(ItemInput::Rating);
+                            holder.rating.map_or("".to_string(), |r| r.to_string())
+                        }
+                        _ => {
+                            *input = Some(ItemInput::Notes);
+                            holder.notes.clone().unwrap_or_default()
+                        }
+                    };
+                    self.side_input = TextArea::from([text]);
+                    Navigation::EnterSideInput
+                }
+                None => Navigation::DoNotihing,
+            },
+            KeyCode::Char('S') => {
+                if let Some(item) = &mut self.search.get_selected_item_mut() {
+                    item.input = Some(ItemInput::Sale);
+                    Navigation::EnterSideInput
+                } else {
+                    Navigation::DoNotihing
+                }
+            }
+            KeyCode::Char('t') => {
+                if let Some(item) = &mut self.search.get_selected_item_mut() {
+                    item.input = Some(ItemInput::Trade);
                     Navigation::EnterSideInput
                 } else {
                     Navigation::DoNotihing
                 }
             }
-            KeyCode::Enter => Navigation::InputSubmit,
+            KeyCode::Char(c @ ('C' | 'P' | 'R' | 'X')) => {
+                let event_type = match c {
+                    'C' => ItemEventType::Cleaned,
+                    'P' => ItemEventType::Played,
+                    'R' => ItemEventType::Repaired,
+                    _ => ItemEventType::Lost,
+                };
+                if let Some(item) = &mut self.search.get_selected_item_mut() {
+                    item.input = Some(ItemInput::Event(event_type));
+                    Navigation::EnterSideInput
+                } else {
+                    Navigation::DoNotihing
+                }
+            }
+            KeyCode::Char('r') => {
+                if self.search.is_item_selected() {
+                    self.return_selected_item()?;
+                    self.message_box = "Item returned".to_string();
+                }
+                Navigation::DoNotihing
+            }
+            KeyCode::Char('E') => match self.search.list.selected() {
+                Some(selected) if self.database.contains_id(selected.record.id) => {
+                    self.editor = Editor::from_record(&selected.record);
+                    Navigation::NavigatePage(AppPages::Editor)
+                }
+                Some(_) => {
+                    self.message_box = "Save the record before editing it".to_string();
+                    Navigation::DoNotihing
+                }
+                None => {
+                    self.message_box = "Record not selected".to_string();
+                    Navigation::DoNotihing
+                }
+            },
+            KeyCode::Char('I') => {
+                self.fetch_selected_cover()?;
+                self.message_box = "Cover downloaded".to_string();
+                Navigation::DoNotihing
+            }
+            KeyCode::Char('k') => {
+                match self.search.list.selected() {
+                    Some(selected) if selected.record.is_local() => {
+                        self.link_record = Some(selected.record.clone());
+                        self.message_box =
+                            "Select a release on the Web search page to link".to_string();
+                    }
+                    Some(_) => self.message_box = "Record already from Discogs".to_string(),
+                    None => self.message_box = "Record not selected".to_string(),
+                }
+                Navigation::DoNotihing
+            }
+            KeyCode::Enter => match self.search.get_selected_item_mut() {
+                Some(item) if item.selected_event.is_some() => {
+                    let text = item
+                        .selected_event
+                        .and_then(|e| item.item.events.get(e))
+                        .map(|e| e.to_edit_text())
+                        .unwrap_or_default();
+                    item.input = Some(ItemInput::EditEvent);
+                    self.side_input = TextArea::from([text]);
+                    Navigation::EnterSideInput
+                }
+                _ => Navigation::InputSubmit,
+            },
             _ => Navigation::DoNotihing,
         })
     }
@@ -135,6 +318,6 @@ impl App<'_> {
     // pub fn search_page_set_selected(&mut self, holder: ItemHolder) {
     //     self.search.is_saved = self.database.contains(&holder.record);
     //     self.search.detail_offset = 0;
-    //     self.search.selected = Some(holder.to_stateful());
+    //     self.search.selected = Some(StatefulItemHolder::from(holder));
     // }
 }
@@ -0,0 +1,479 @@
+//! Sleeve labels with a QR code per copy, laid out on sticker sheets.
+
+use qrcodegen::{QrCode, QrCodeEcc};
+
+use crate::{
+    models::{
+        error::{AppError, Result},
+        item_holder::{Item, ItemHolder},
+    },
+    search::SearchQuery,
+};
+
+/// Text encoded in the QR code of a label. Upper case so it fits the compact
+/// alphanumeric QR mode, e.g. "RC:249504/2".
+pub fn label_code(record_id: i64, item_id: i64) -> String {
+    format!("RC:{}/{}", record_id, item_id)
+}
+
+/// Reads a scanned label code back into the record and item id.
+pub fn parse_label_code(text: &str) -> Option<(i64, i64)> {
+    let text = text.trim();
+    let (prefix, ids) = text.split_at(text.find(':')? + 1);
+    if !prefix.eq_ignore_ascii_case("rc:") {
+        return None;
+    }
+    let (record_id, item_id) = ids.split_once('/')?;
+    Some((record_id.parse().ok()?, item_id.parse().ok()?))
+}
+
+/// Label sheet geometry in millimetres, measured from the top left corner of the page.
+pub struct LabelLayout {
+    pub name: &'static str,
+    pub page_width: f64,
+    pub page_height: f64,
+    pub columns: usize,
+    pub rows: usize,
+    pub label_width: f64,
+    pub label_height: f64,
+    pub top: f64,
+    pub left: f64,
+    /// Distance between the left edges of neighbouring labels.
+    pub horizontal_pitch: f64,
+    /// Distance between the top edges of labels above each other.
+    pub vertical_pitch: f64,
+}
+
+/// Sticker sheets that labels can be printed on.
+pub const LAYOUTS: [LabelLayout; 5] = [
+    LabelLayout {
+        name: "L7160",
+        page_width: 210.0,
+        page_height: 297.0,
+        columns: 3,
+        rows: 7,
+        label_width: 63.5,
+        label_height: 38.1,
+        top: 15.15,
+        left: 7.2,
+        horizontal_pitch: 66.0,
+        vertical_pitch: 38.1,
+    },
+    LabelLayout {
+        name: "L7163",
+        page_width: 210.0,
+        page_height: 297.0,
+        columns: 2,
+        rows: 7,
+        label_width: 99.1,
+        label_height: 38.1,
+        top: 15.15,
+        left: 4.65,
+        horizontal_pitch: 101.6,
+        vertical_pitch: 38.1,
+    },
+    LabelLayout {
+        name: "L7165",
+        page_width: 210.0,
+        page_height: 297.0,
+        columns: 2,
+        rows: 4,
+        label_width: 99.1,
+        label_height: 67.7,
+        top: 13.1,
+        left: 4.65,
+        horizontal_pitch: 101.6,
+        vertical_pitch: 67.7,
+    },
+    LabelLayout {
+        name: "5160",
+        page_width: 215.9,
+        page_height: 279.4,
+        columns: 3,
+        rows: 10,
+        label_width: 66.675,
+        label_height: 25.4,
+        top: 12.7,
+        left: 4.7625,
+        horizontal_pitch: 69.85,
+        vertical_pitch: 25.4,
+    },
+    LabelLayout {
+        name: "5163",
+        page_width: 215.9,
+        page_height: 279.4,
+        columns: 2,
+        rows: 5,
+        label_width: 101.6,
+        label_height: 50.8,
+        top: 12.7,
+        left: 3.96875,
+        horizontal_pitch: 104.775,
+        vertical_pitch: 50.8,
+    },
+];
+
+impl LabelLayout {
+    /// The layout called `name`, ignoring case.
+    pub fn find(name: &str) -> Result<&'static LabelLayout> {
+        LAYOUTS
+            .iter()
+            .find(|l| l.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Unknown label layout \"{}\", expected one of {}",
+                    name,
+                    LAYOUTS.map(|l| l.name).join(", ")
+                ))
+            })
+    }
+
+    fn per_page(&self) -> usize {
+        self.columns * self.rows
+    }
+}
+
+/// What is printed on the label of one copy.
+pub struct SleeveLabel {
+    pub code: String,
+    pub artist: String,
+    pub title: String,
+    pub catno: String,
+    pub location: String,
+}
+
+impl SleeveLabel {
+    /// The label of a copy of the record.
+    pub fn new(holder: &ItemHolder, item: &Item) -> Self {
+        let record = &holder.record;
+        let names: Vec<_> = record.artists.iter().map(|a| a.name.as_str()).collect();
+        let catnos: Vec<_> = record.labels.iter().map(|l| l.catno.as_str()).collect();
+        SleeveLabel {
+            code: label_code(record.id, item.id),
+            artist: names.join(", "),
+            title: record.title.clone(),
+            catno: catnos.join(", "),
+            location: item
+                .location
+                .as_ref()
+                .map_or("".to_string(), |l| l.to_string()),
+        }
+    }
+}
+
+/// Labels for the copies of `holders` passing the item filters of `query`,
+/// copies that left the collection are only included with `all`.
+pub fn sleeve_labels(holders: &[ItemHolder], query: &SearchQuery, all: bool) -> Vec<SleeveLabel> {
+    holders
+        .iter()
+        .flat_map(|holder| {
+            holder
+                .items
+                .iter()
+                .filter(|item| all || item.is_in_collection())
+                .filter(|item| query.matches_item(holder, item))
+                .map(move |item| SleeveLabel::new(holder, item))
+        })
+        .collect()
+}
+
+/// A drawing instruction in millimetres, text is positioned by its baseline.
+enum Shape {
+    Rect {
+        x: f64,
+        y: f64,
+        size: f64,
+    },
+    Text {
+        x: f64,
+        y: f64,
+        size: f64,
+        bold: bool,
+        text: String,
+    },
+}
+
+const PT_PER_MM: f64 = 72.0 / 25.4;
+
+/// Lays the labels out on as many sheets as needed, `skip` leaves the first
+/// labels of a partly used sheet empty.
+fn sheets(labels: &[SleeveLabel], layout: &LabelLayout, skip: usize) -> Result<Vec<Vec<Shape>>> {
+    let mut pages: Vec<Vec<Shape>> = vec![];
+    for (index, label) in labels.iter().enumerate() {
+        let position = index + skip;
+        let page = position / layout.per_page();
+        pages.resize_with(page + 1, Vec::new);
+        let slot = position % layout.per_page();
+        let x = layout.left + (slot % layout.columns) as f64 * layout.horizontal_pitch;
+        let y = layout.top + (slot / layout.columns) as f64 * layout.vertical_pitch;
+        draw_label(&mut pages[page], label, layout, x, y)?;
+    }
+    Ok(pages)
+}
+
+fn draw_label(
+    shapes: &mut Vec<Shape>,
+    label: &SleeveLabel,
+    layout: &LabelLayout,
+    x: f64,
+    y: f64,
+) -> Result<()> {
+    let padding = 3.0;
+    let qr = QrCode::encode_text(&label.code, QrCodeEcc::Medium)
+        .map_err(|_| AppError::Validation("Label code too long for a QR code".to_string()))?;
+    let qr_size = (layout.label_height - 2.0 * padding).min(layout.label_width * 0.35);
+    // Scanners need a blank margin of a few modules around the code.
+    let module = qr_size / (qr.size() + 4) as f64;
+    for row in 0..qr.size() {
+        for column in 0..qr.size() {
+            if qr.get_module(column, row) {
+                shapes.push(Shape::Rect {
+                    x: x + padding + (column + 2) as f64 * module,
+                    y: y + padding + (row + 2) as f64 * module,
+                    size: module,
+                });
+            }
+        }
+    }
+
+    let text_x = x + 2.0 * padding + qr_size;
+    let text_width = x + layout.label_width - padding - text_x;
+    let size = (layout.label_height * PT_PER_MM / 9.0).min(9.0);
+    let line_height = size / PT_PER_MM * 1.3;
+    let lines = [
+        (&label.title, true),
+        (&label.artist, false),
+        (&label.catno, false),
+        (&label.location, false),
+        (&label.code, false),
+    ];
+    let mut baseline = y + padding + line_height;
+    for (text, bold) in lines {
+        if text.is_empty() {
+            continue;
+        }
+        if baseline > y + layout.label_height - padding / 2.0 {
+            break;
+        }
+        shapes.push(Shape::Text {
+            x: text_x,
+            y: baseline,
+            size,
+            bold,
+            text: fit_text(text, text_width, size),
+        });
+        baseline += line_height;
+    }
+    Ok(())
+}
+
+/// Shortens the text to about `width` mm, assuming an average glyph is half the font size wide.
+fn fit_text(text: &str, width: f64, size: f64) -> String {
+    let max = (width * PT_PER_MM / (size * 0.5)) as usize;
+    match text.chars().count() > max {
+        true => text.chars().take(max.saturating_sub(3)).collect::<String>() + "...",
+        false => text.to_string(),
+    }
+}
+
+/// One SVG document per sheet.
+pub fn to_svg(labels: &[SleeveLabel], layout: &LabelLayout, skip: usize) -> Result<Vec<String>> {
+    Ok(sheets(labels, layout, skip)?
+        .iter()
+        .map(|shapes| {
+            let mut svg = format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" height=\"{h}mm\" viewBox=\"0 0 {w} {h}\" font-family=\"Helvetica, Arial, sans-serif\">\n",
+                w = layout.page_width,
+                h = layout.page_height
+            );
+            for shape in shapes {
+                svg += &match shape {
+                    Shape::Rect { x, y, size } => format!(
+                        "<rect x=\"{:.3}\" y=\"{:.3}\" width=\"{:.3}\" height=\"{:.3}\"/>\n",
+                        x, y, size, size
+                    ),
+                    Shape::Text { x, y, size, bold, text } => format!(
+                        "<text x=\"{:.3}\" y=\"{:.3}\" font-size=\"{:.3}\"{}>{}</text>\n",
+                        x,
+                        y,
+                        size / PT_PER_MM,
+                        if *bold { " font-weight=\"bold\"" } else { "" },
+                        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+                    ),
+                };
+            }
+            svg + "</svg>\n"
+        })
+        .collect())
+}
+
+/// A PDF with one page per sheet, using the standard Helvetica fonts so nothing has to be embedded.
+pub fn to_pdf(labels: &[SleeveLabel], layout: &LabelLayout, skip: usize) -> Result<Vec<u8>> {
+    let pages = sheets(labels, layout, skip)?;
+    let width = layout.page_width * PT_PER_MM;
+    let height = layout.page_height * PT_PER_MM;
+
+    // 1 catalog, 2 page tree, 3 and 4 fonts, then a page and its content per sheet.
+    let mut objects: Vec<Vec<u8>> = vec![];
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", 5 + 2 * i))
+        .collect();
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    objects.push(
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+    );
+    for font in ["Helvetica", "Helvetica-Bold"] {
+        objects.push(
+            format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font
+            )
+            .into_bytes(),
+        );
+    }
+    for (i, shapes) in pages.iter().enumerate() {
+        let mut content = b"0 g\n".to_vec();
+        for shape in shapes {
+            match shape {
+                Shape::Rect { x, y, size } => content.extend(
+                    format!(
+                        "{:.3} {:.3} {:.3} {:.3} re f\n",
+                        x * PT_PER_MM,
+                        height - (y + size) * PT_PER_MM,
+                        size * PT_PER_MM,
+                        size * PT_PER_MM
+                    )
+                    .into_bytes(),
+                ),
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    bold,
+                    text,
+                } => {
+                    content.extend(
+                        format!(
+                            "BT /F{} {:.2} Tf {:.3} {:.3} Td (",
+                            if *bold { 2 } else { 1 },
+                            size,
+                            x * PT_PER_MM,
+                            height - y * PT_PER_MM
+                        )
+                        .into_bytes(),
+                    );
+                    content.extend(pdf_string(text));
+                    content.extend(b") Tj ET\n");
+                }
+            }
+        }
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                width,
+                height,
+                6 + 2 * i
+            )
+            .into_bytes(),
+        );
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", i + 1).into_bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .into_bytes(),
+    );
+    Ok(pdf)
+}
+
+/// Latin-1 bytes of the text with the PDF string delimiters escaped, other characters become "?".
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => bytes.extend([b'\\', c as u8]),
+            c if (c as u32) < 256 => bytes.push(c as u8),
+            _ => bytes.push(b'?'),
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::record::Record;
+
+    fn labels(count: usize) -> Vec<SleeveLabel> {
+        let mut holder = ItemHolder::new_with_item(Record {
+            id: 249504,
+            title: "Rumours".to_string(),
+            ..Record::default()
+        });
+        for _ in 1..count {
+            holder.add_item();
+        }
+        sleeve_labels(&[holder], &SearchQuery::parse("", &[]), false)
+    }
+
+    #[test]
+    fn label_codes_read_back() {
+        assert_eq!(label_code(249504, 2), "RC:249504/2");
+        assert_eq!(parse_label_code(&label_code(249504, 2)), Some((249504, 2)));
+        assert_eq!(parse_label_code(" rc:-3/1 "), Some((-3, 1)));
+        assert_eq!(parse_label_code("RC:x/2"), None);
+        assert_eq!(parse_label_code("RC:1"), None);
+        assert_eq!(parse_label_code("XY:1/2"), None);
+        assert_eq!(parse_label_code("Rumours"), None);
+    }
+
+    #[test]
+    fn skipped_labels_push_the_rest_to_the_next_sheet() {
+        let layout = LabelLayout::find("l7165").unwrap();
+        let labels = labels(8);
+        assert_eq!(labels.len(), 8);
+        assert_eq!(sheets(&labels, layout, 0).unwrap().len(), 1);
+        assert_eq!(sheets(&labels, layout, 1).unwrap().len(), 2);
+        assert_eq!(to_svg(&labels, layout, 7).unwrap().len(), 2);
+        assert!(LabelLayout::find("A4").is_err());
+    }
+
+    #[test]
+    fn pdf_has_a_page_per_sheet() {
+        let layout = LabelLayout::find("L7165").unwrap();
+        let pdf = to_pdf(&labels(9), layout, 0).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("(Rumours) Tj"));
+        assert!(text.ends_with("%%EOF\n"));
+
+        let svg = &to_svg(&labels(1), layout, 0).unwrap()[0];
+        assert!(svg.contains(">RC:249504/0</text>"));
+    }
+}
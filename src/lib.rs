@@ -0,0 +1,29 @@
+//! Catalog of a record collection: the data model, its JSON database and the
+//! services built on it, without any user interface.
+//!
+//! - [`models`] holds the records, copies and their event history.
+//! - [`database::Database`] loads, searches and saves a collection. All changes to
+//!   copies should go through [`database::Database::modify_item`] and the methods on
+//!   [`models::item_holder::Item`] so every front end applies the same rules.
+//! - [`search::SearchQuery`] parses the local search syntax.
+//! - [`discogs::DiscogsClient`] fetches releases and prices from Discogs.
+//! - [`borrowers`], [`merge`], [`stats`], [`valuation`] and [`covers`] add loans,
+//!   catalog merging, statistics, collection value and cover images.
+//! - [`export`], [`labels`], [`site`] and [`lines`] turn a collection into
+//!   reports, printable labels, a static website and plain text.
+//!
+//! The terminal app and the command line in this package are built on this crate.
+
+pub mod borrowers;
+pub mod covers;
+pub mod database;
+pub mod discogs;
+pub mod export;
+pub mod labels;
+pub mod lines;
+pub mod merge;
+pub mod models;
+pub mod search;
+pub mod site;
+pub mod stats;
+pub mod valuation;
@@ -0,0 +1,266 @@
+//! Plain text rendering of records, shared by the app and the command line.
+
+use std::vec;
+
+use crate::models::{
+    annotations::Annotations,
+    record::{Format, Record, Track},
+};
+
+enum TM {
+    Line(String),
+    Module(Tab),
+}
+
+impl TM {
+    fn line_with_title(title: &str, items: Vec<String>, delimiter: &str) -> TM {
+        let mut out = "".to_string();
+        out += title;
+        for i in items {
+            out += &i;
+            out += delimiter
+        }
+        out = out.trim_end_matches(delimiter).to_string();
+        TM::Line(out)
+    }
+
+    fn blank() -> TM {
+        TM::Line("".to_string())
+    }
+}
+
+struct Tab {
+    pub children: Vec<TM>,
+}
+
+impl Tab {
+    fn new(children: Vec<TM>) -> Self {
+        Tab { children }
+    }
+
+    #[allow(dead_code)]
+    fn single(child: TM) -> Self {
+        Tab {
+            children: vec![child],
+        }
+    }
+
+    fn from_strings(children: Vec<String>) -> Self {
+        Tab {
+            children: children.into_iter().map(TM::Line).collect(),
+        }
+    }
+
+    fn get_lines(self, depth: usize) -> Vec<String> {
+        let lines: Vec<String> = self
+            .children
+            .into_iter()
+            .flat_map(|c| match c {
+                TM::Line(l) => vec![l],
+                TM::Module(t) => t.get_lines(depth + 1),
+            })
+            .map(|s| Tab::get_indent(depth) + &s)
+            .collect();
+
+        lines
+    }
+    fn get_indent(depth: usize) -> String {
+        let mut out: String = "".to_owned();
+        for _ in 0..depth {
+            out += "  ";
+        }
+        out
+    }
+}
+
+impl Record {
+    /// The record details as shown in the app, one entry per line.
+    pub fn get_lines(&self) -> Vec<String> {
+        let mut main_tab_module = Tab::new(vec![
+            TM::blank(),
+            TM::Line(self.title.clone()),
+            TM::blank(),
+            TM::Line("Year: ".to_owned() + &self.year.to_string()),
+        ]);
+        let optional = [
+            ("Released: ", &self.released),
+            ("Country: ", &self.country),
+            ("Data quality: ", &self.data_quality),
+        ];
+        for (title, value) in optional {
+            if let Some(value) = value {
+                main_tab_module
+                    .children
+                    .push(TM::Line(title.to_string() + value));
+            }
+        }
+        main_tab_module.children.append(&mut vec![
+            TM::Line("Artists:".to_string()),
+            TM::Module(Tab::from_strings(
+                self.artists.iter().map(|a| a.name.clone()).collect(),
+            )),
+            TM::Line("Formats:".to_string()),
+            TM::Module(Tab::from_strings(
+                self.formats.iter().map(|f| f.get_line()).collect(),
+            )),
+            TM::Line("Labels:".to_string()),
+            TM::Module(Tab::from_strings(
+                self.labels
+                    .iter()
+                    .map(|l| format!("{} - {}", l.name, l.catno))
+                    .collect(),
+            )),
+            TM::line_with_title("Genres: ", self.genres.clone(), " | "),
+            TM::line_with_title("Styles: ", self.styles.clone(), " | "),
+        ]);
+
+        if !self.series.is_empty() {
+            main_tab_module.children.append(&mut vec![
+                TM::Line("Series:".to_string()),
+                TM::Module(Tab::from_strings(
+                    self.series
+                        .iter()
+                        .map(|s| format!("{} - {}", s.name, s.catno))
+                        .collect(),
+                )),
+            ]);
+        }
+        if !self.companies.is_empty() {
+            main_tab_module.children.append(&mut vec![
+                TM::Line("Companies:".to_string()),
+                TM::Module(Tab::from_strings(
+                    self.companies
+                        .iter()
+                        .map(|c| match c.catno.as_str() {
+                            "" => format!("{} - {}", c.entity_type_name, c.name),
+                            catno => format!("{} - {} ({})", c.entity_type_name, c.name, catno),
+                        })
+                        .collect(),
+                )),
+            ]);
+        }
+        if !self.extraartists.is_empty() {
+            main_tab_module.children.append(&mut vec![
+                TM::Line("Credits:".to_string()),
+                TM::Module(Tab::from_strings(
+                    self.extraartists
+                        .iter()
+                        .map(|a| match a.tracks.as_deref() {
+                            None | Some("") => format!("{} - {}", a.role, a.name),
+                            Some(tracks) => format!("{} - {} [{}]", a.role, a.name, tracks),
+                        })
+                        .collect(),
+                )),
+            ]);
+        }
+
+        main_tab_module
+            .children
+            .append(&mut vec![TM::Line("Tracklist: ".to_string()), TM::blank()]);
+
+        self.tracklist
+            .iter()
+            .map(|t| t.get_module())
+            .for_each(|m| main_tab_module.children.push(m));
+
+        if let Some(notes) = &self.notes {
+            main_tab_module.children.append(&mut vec![
+                TM::blank(),
+                TM::Line("Notes:".to_string()),
+                TM::Module(Tab::from_strings(
+                    notes.lines().map(|l| l.to_string()).collect(),
+                )),
+            ]);
+        }
+        if !self.videos.is_empty() {
+            main_tab_module.children.append(&mut vec![
+                TM::blank(),
+                TM::Line("Videos:".to_string()),
+                TM::Module(Tab::from_strings(
+                    self.videos
+                        .iter()
+                        .map(|v| {
+                            format!(
+                                "{} [{}:{:02}] {}",
+                                v.title,
+                                v.duration / 60,
+                                v.duration % 60,
+                                v.uri
+                            )
+                        })
+                        .collect(),
+                )),
+            ]);
+        }
+
+        main_tab_module.children.append(&mut vec![
+            TM::blank(),
+            TM::blank(),
+            TM::Line(format!("Id: {:?}", self.id)),
+        ]);
+        if !self.images.is_empty() {
+            main_tab_module
+                .children
+                .push(TM::Line(format!("Images: {}", self.images.len())));
+        }
+
+        main_tab_module.get_lines(1)
+    }
+}
+
+impl Format {
+    /// E.g. "2 x Vinyl (LP, Album)".
+    pub fn get_line(&self) -> String {
+        let mut line = match self.qty.as_deref() {
+            None | Some("") | Some("1") => self.name.clone(),
+            Some(qty) => format!("{} x {}", qty, self.name),
+        };
+        if !self.descriptions.is_empty() {
+            line += &format!(" ({})", self.descriptions.join(", "));
+        }
+        line
+    }
+}
+
+impl Track {
+    fn get_module(&self) -> TM {
+        let position = match self.position.as_str() {
+            "" => "".to_string(),
+            _ => self.position.clone() + ".",
+        };
+        let duration = match self.duration.as_str() {
+            "" => "".to_string(),
+            _ => " [".to_string() + &self.duration + "] ",
+        };
+        let mut tab = Tab::new(vec![TM::Line(position + &duration + &self.title)]);
+        if let Some(artists) = &self.extraartists {
+            let artists_module = TM::Module(Tab::from_strings(
+                artists.iter().map(|a| a.name.clone()).collect(),
+            ));
+            tab.children.push(artists_module);
+            tab.children.push(TM::Line("".to_string()));
+        }
+        if let Some(sub_tracks) = &self.sub_tracks {
+            sub_tracks
+                .iter()
+                .map(|s| s.get_module())
+                .for_each(|m| tab.children.push(m));
+            tab.children.push(TM::Line("".to_string()));
+        };
+        TM::Module(tab)
+    }
+}
+
+impl Annotations {
+    /// Tags and custom fields as "Tags: ..." and "name: value" lines.
+    pub fn get_lines(&self) -> Vec<String> {
+        let mut lines = vec![];
+        if !self.tags.is_empty() {
+            lines.push(format!("Tags: {}", self.tags.join(", ")));
+        }
+        for (name, value) in &self.fields {
+            lines.push(format!("{}: {}", name, value));
+        }
+        lines
+    }
+}
@@ -1,36 +1,65 @@
-mod database;
-mod discogs;
+mod cli;
+mod crash;
 mod gui;
 mod inputer;
-mod models;
-use std::fs;
+mod server;
+mod state;
+use std::{env, process, sync::mpsc::Receiver};
 
-use models::{app::App, error::Result, record::Label, settings::Settings};
+use crossterm::event::KeyEvent;
 
-fn main() -> Result<()> {
+use inputer::inputer::CustomEvent;
+use record_catalog::models::{
+    error::{AppError, Result},
+    record::Label,
+    settings::Settings,
+};
+use state::app::App;
+
+fn main() {
     // return test();
 
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        process::exit(cli::run(&args));
+    }
+
+    if let Err(e) = start_app() {
+        eprintln!("{}", e);
+        process::exit(cli::EXIT_FAILURE);
+    }
+}
+
+fn start_app() -> Result<()> {
+    let settings = Settings::load()?;
+    crash::install_panic_hook(&settings.crash_log_path);
+    let mut app = App::new(settings)?;
+
     let receiver = inputer::inputer::start();
 
-    let mut terminal = gui::terminal::start()?;
+    let result = run(&mut app, &receiver);
+    if let Err(e) = &result {
+        if let Some(path) = crash::write_report(&e.to_string()) {
+            eprintln!("Crash report written to {}", path);
+        }
+    }
+    result
+}
 
-    let mut app = App::new(load_settings()?)?;
+/// Draws and handles input until the user quits. The terminal is restored when this
+/// returns, also on errors, before the error is printed.
+fn run(app: &mut App, receiver: &Receiver<CustomEvent<KeyEvent>>) -> Result<()> {
+    let mut terminal = gui::terminal::start()?;
 
     loop {
         app.render(&mut terminal)?;
-        if app.input(receiver.recv()?)? {
-            break;
+        let event = receiver
+            .recv()
+            .map_err(|_| AppError::Terminal("Keyboard input stopped".to_string()))?;
+        if app.input(event) {
+            return Ok(());
         }
     }
-
-    gui::terminal::end(&mut terminal)?;
-
-    Ok(())
-}
-
-fn load_settings() -> Result<Settings> {
-    let string = fs::read_to_string("settings.json")?;
-    Ok(serde_json::from_str::<Settings>(&string)?)
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,351 @@
+//! Merging two versions of the collection copy by copy.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::models::item_holder::{Item, ItemEvent, ItemEventType, ItemHolder};
+
+/// Something the right catalog adds to the left one.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MergeChange {
+    Holder {
+        id: i64,
+        title: String,
+    },
+    Item {
+        holder_id: i64,
+        item_id: i64,
+    },
+    Events {
+        holder_id: i64,
+        item_id: i64,
+        count: usize,
+    },
+}
+
+/// Differences that can't be resolved automatically and need a human to look at them.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MergeConflict {
+    /// Both catalogs have the record but its metadata differs, the left one is kept.
+    Record { id: i64, title: String },
+    /// Both catalogs use the same item id for copies created at different times,
+    /// the right copy is kept under a new id.
+    Item {
+        holder_id: i64,
+        item_id: i64,
+        new_id: i64,
+    },
+    /// The same copy was graded differently, the left grades are kept.
+    Condition { holder_id: i64, item_id: i64 },
+    /// Both catalogs rate or review the record differently, the left ones are kept.
+    Review { id: i64, title: String },
+}
+
+/// What a merge changed and what it couldn't decide.
+#[derive(Default, Serialize)]
+pub struct MergeReport {
+    pub changes: Vec<MergeChange>,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// Merges `right` into `left`, matching holders by record id and items by item id.
+pub fn merge(left: &[ItemHolder], right: &[ItemHolder]) -> (Vec<ItemHolder>, MergeReport) {
+    let mut merged = left.to_vec();
+    let mut report = MergeReport::default();
+
+    for right_holder in right {
+        let holder = match merged
+            .iter_mut()
+            .find(|ih| ih.record.id == right_holder.record.id)
+        {
+            Some(holder) => holder,
+            None => {
+                report.changes.push(MergeChange::Holder {
+                    id: right_holder.record.id,
+                    title: right_holder.record.title.clone(),
+                });
+                merged.push(right_holder.clone());
+                continue;
+            }
+        };
+
+        holder.annotations.merge(&right_holder.annotations);
+        let rating_differs = holder.rating.is_some()
+            && right_holder.rating.is_some()
+            && holder.rating != right_holder.rating;
+        let notes_differ = holder.notes.is_some()
+            && right_holder.notes.is_some()
+            && holder.notes != right_holder.notes;
+        if rating_differs || notes_differ {
+            report.conflicts.push(MergeConflict::Review {
+                id: holder.record.id,
+                title: holder.record.title.clone(),
+            });
+        }
+        holder.rating = holder.rating.or(right_holder.rating);
+        holder.notes = holder.notes.take().or_else(|| right_holder.notes.clone());
+        if holder.record != right_holder.record {
+            report.conflicts.push(MergeConflict::Record {
+                id: holder.record.id,
+                title: holder.record.title.clone(),
+            });
+        }
+
+        for right_item in &right_holder.items {
+            let holder_id = holder.record.id;
+            // A copy already merged under a new id is found by its creation instead.
+            let same_copy = holder
+                .items
+                .iter()
+                .position(|i| i.id == right_item.id && same_creation(i, right_item))
+                .or_else(|| {
+                    holder
+                        .items
+                        .iter()
+                        .position(|i| same_creation(i, right_item))
+                });
+            let id_taken = holder.items.iter().any(|i| i.id == right_item.id);
+            match same_copy.map(|index| &mut holder.items[index]) {
+                Some(item) => {
+                    item.annotations.merge(&right_item.annotations);
+                    if !merge_conditions(item, right_item) {
+                        report.conflicts.push(MergeConflict::Condition {
+                            holder_id,
+                            item_id: item.id,
+                        });
+                    }
+                    let count = merge_events(item, right_item);
+                    if count > 0 {
+                        report.changes.push(MergeChange::Events {
+                            holder_id,
+                            item_id: item.id,
+                            count,
+                        });
+                    }
+                }
+                None if id_taken => {
+                    let new_id = holder.next_item_id();
+                    let mut item = right_item.clone();
+                    item.id = new_id;
+                    holder.items.push(item);
+                    report.conflicts.push(MergeConflict::Item {
+                        holder_id,
+                        item_id: right_item.id,
+                        new_id,
+                    });
+                }
+                None => {
+                    holder.items.push(right_item.clone());
+                    report.changes.push(MergeChange::Item {
+                        holder_id,
+                        item_id: right_item.id,
+                    });
+                }
+            }
+        }
+    }
+
+    (merged, report)
+}
+
+/// Whether both items are the same copy, created at the same second. The file keeps
+/// dates in seconds while events created in memory are more precise.
+fn same_creation(item: &Item, other: &Item) -> bool {
+    let created = |item: &Item| {
+        item.events
+            .iter()
+            .find(|e| e.event_type == ItemEventType::Created)
+            .cloned()
+    };
+    match (created(item), created(other)) {
+        (Some(a), Some(b)) => same_event(&a, &b),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Events are equal when they only differ below a second.
+fn same_event(event: &ItemEvent, other: &ItemEvent) -> bool {
+    event.date.timestamp() == other.date.timestamp()
+        && event.event_type == other.event_type
+        && event.message == other.message
+        && event.payload == other.payload
+}
+
+/// Fills in grades only known on the other side, returns false when both sides disagree.
+fn merge_conditions(item: &mut Item, other: &Item) -> bool {
+    let mut agree = true;
+    for (grade, other_grade) in [
+        (&mut item.media_condition, other.media_condition),
+        (&mut item.sleeve_condition, other.sleeve_condition),
+    ] {
+        match (*grade, other_grade) {
+            (None, Some(_)) => *grade = other_grade,
+            (Some(a), Some(b)) if a != b => agree = false,
+            _ => {}
+        }
+    }
+    agree
+}
+
+/// Adds the events missing from `item` and keeps the history sorted by date.
+fn merge_events(item: &mut Item, other: &Item) -> usize {
+    let missing: Vec<_> = other
+        .events
+        .iter()
+        .filter(|e| !item.events.iter().any(|own| same_event(own, e)))
+        .cloned()
+        .collect();
+    let count = missing.len();
+    item.events.extend(missing);
+    item.events.sort_by_key(|e| e.date);
+    count
+}
+
+impl fmt::Display for MergeChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeChange::Holder { id, title } => write!(f, "+ record {} {}", id, title),
+            MergeChange::Item { holder_id, item_id } => {
+                write!(f, "+ item {}#{}", holder_id, item_id)
+            }
+            MergeChange::Events {
+                holder_id,
+                item_id,
+                count,
+            } => write!(
+                f,
+                "~ item {}#{}: {} events added",
+                holder_id, item_id, count
+            ),
+        }
+    }
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeConflict::Record { id, title } => write!(
+                f,
+                "! record {} {}: metadata differs, kept the left version",
+                id, title
+            ),
+            MergeConflict::Item {
+                holder_id,
+                item_id,
+                new_id,
+            } => write!(
+                f,
+                "! item {}#{}: different copies share the id, right copy kept as #{}",
+                holder_id, item_id, new_id
+            ),
+            MergeConflict::Condition { holder_id, item_id } => write!(
+                f,
+                "! item {}#{}: graded differently, kept the left grades",
+                holder_id, item_id
+            ),
+            MergeConflict::Review { id, title } => write!(
+                f,
+                "! record {} {}: rated or reviewed differently, kept the left version",
+                id, title
+            ),
+        }
+    }
+}
+
+impl fmt::Display for MergeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        for conflict in &self.conflicts {
+            writeln!(f, "{}", conflict)?;
+        }
+        write!(
+            f,
+            "{} changes, {} conflicts",
+            self.changes.len(),
+            self.conflicts.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::{database::Database, models::record::Record};
+
+    /// A holder with two copies whose events carry sub-second dates, like ones created in the app.
+    fn holder_in_memory() -> ItemHolder {
+        let mut holder = ItemHolder::new(Record {
+            id: 42,
+            title: "Rumours".to_string(),
+            ..Record::default()
+        });
+        for id in [1, 2] {
+            let mut item = Item::new_with_id(id);
+            for event in item.events.iter_mut() {
+                event.date = Utc.timestamp_opt(1_700_000_000 + id, 123_456_789).unwrap();
+            }
+            let mut played = ItemEvent::new(ItemEventType::Played);
+            played.date = Utc.timestamp_opt(1_700_000_100, 987_654_321).unwrap();
+            item.events.push(played);
+            holder.items.push(item);
+        }
+        holder
+    }
+
+    #[test]
+    fn merging_with_the_saved_file_changes_nothing() {
+        let data = vec![holder_in_memory()];
+        let path = std::env::temp_dir().join(format!("merge-test-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        Database::write_data(path, &data).unwrap();
+        let saved = Database::read_data(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let (merged, report) = merge(&data, &saved);
+        assert!(report.changes.is_empty());
+        assert!(report.conflicts.is_empty());
+        assert_eq!(merged, data);
+    }
+
+    #[test]
+    fn importing_a_conflicting_copy_twice_adds_it_once() {
+        let left = vec![holder_in_memory()];
+        let mut right = left.clone();
+        right[0].items.truncate(1);
+        for event in right[0].items[0].events.iter_mut() {
+            event.date = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        }
+
+        let (once, report) = merge(&left, &right);
+        assert_eq!(once[0].items.len(), 3);
+        assert_eq!(report.conflicts.len(), 1);
+
+        let (twice, report) = merge(&once, &right);
+        assert_eq!(twice, once);
+        assert!(report.changes.is_empty());
+        assert!(report.conflicts.is_empty());
+    }
+
+    #[test]
+    fn differing_reviews_are_conflicts() {
+        let mut left = holder_in_memory();
+        left.rating = Some(4);
+        let mut right = left.clone();
+        right.rating = Some(2);
+
+        let (merged, report) = merge(&[left], &[right]);
+        assert_eq!(merged[0].rating, Some(4));
+        assert!(matches!(
+            report.conflicts.as_slice(),
+            [MergeConflict::Review { id: 42, .. }]
+        ));
+    }
+}
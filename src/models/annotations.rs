@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::error::{AppError, Result};
+
+/// Free-form tags and custom field values, kept on both holders and copies.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct Annotations {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Annotations {
+    /// Replaces the tags with a comma separated list, duplicates are dropped.
+    pub fn set_tags(&mut self, text: &str) {
+        self.tags.clear();
+        for tag in text.split(',').map(|t| t.trim()).filter(|t| !t.is_empty()) {
+            if !self.has_tag(tag) {
+                self.tags.push(tag.to_string());
+            }
+        }
+    }
+
+    /// Whether the tag is set, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Sets a field from "name = value" after checking it against the schema,
+    /// an empty value removes the field.
+    pub fn set_field(&mut self, schema: &[CustomField], text: &str) -> Result<()> {
+        let (name, value) = text
+            .split_once('=')
+            .ok_or_else(|| AppError::Validation("Expected \"name = value\"".to_string()))?;
+        let field = schema
+            .iter()
+            .find(|f| f.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Unknown field \"{}\", add it to custom_fields in settings.json",
+                    name.trim()
+                ))
+            })?;
+        match value.trim() {
+            "" => self.fields.remove(&field.name),
+            value => self
+                .fields
+                .insert(field.name.clone(), field.validate(value)?),
+        };
+        Ok(())
+    }
+
+    /// The value of a custom field, the name is matched ignoring case.
+    pub fn get_field(&self, name: &str) -> Option<&String> {
+        self.fields
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// Adds the tags and fields only known to `other`.
+    pub fn merge(&mut self, other: &Annotations) {
+        for tag in &other.tags {
+            if !self.has_tag(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+        for (name, value) in &other.fields {
+            self.fields
+                .entry(name.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+}
+
+/// A user defined field, configured in `settings.json`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct CustomField {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: FieldKind,
+}
+
+/// Type of a custom field, checked when a value is set.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FieldKind {
+    Text,
+    Number,
+    Date,
+    Enum { values: Vec<String> },
+}
+
+impl CustomField {
+    /// Checks a value against the field type and returns it in its stored form.
+    pub fn validate(&self, value: &str) -> Result<String> {
+        let invalid = || AppError::Validation(format!("Invalid {} \"{}\"", self.name, value));
+        Ok(match &self.kind {
+            FieldKind::Text => value.to_string(),
+            FieldKind::Number => value.parse::<f64>().map_err(|_| invalid())?.to_string(),
+            FieldKind::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map_err(|_| invalid())?
+                .to_string(),
+            FieldKind::Enum { values } => values
+                .iter()
+                .find(|v| v.eq_ignore_ascii_case(value))
+                .cloned()
+                .ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "{} must be one of {}",
+                        self.name,
+                        values.join(", ")
+                    ))
+                })?,
+        })
+    }
+}
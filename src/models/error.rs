@@ -1,16 +1,96 @@
-use std::{error, fmt};
+use std::{error, fmt, io, path::Path};
 
-use std::error::Error;
-
-pub type Result<T> = std::result::Result<T, Box<dyn error::Error>>;
+/// Result of everything in the catalog that can fail.
+pub type Result<T> = std::result::Result<T, AppError>;
 
+/// Everything that can go wrong in the catalog, grouped by where it comes from.
+/// Each variant holds a message meant to be shown to the user as is.
 #[derive(Debug)]
-pub struct AppError(pub String);
+pub enum AppError {
+    /// Reading or writing the database, settings or another file failed.
+    Storage(String),
+    /// A request to Discogs or another server failed.
+    Network(String),
+    /// Data couldn't be read, e.g. a corrupt file or an unexpected API response.
+    Parse(String),
+    /// The input or the change was rejected, e.g. lending a copy that is already on loan.
+    Validation(String),
+    /// A record, copy or other entry asked for isn't there.
+    NotFound(String),
+    /// `settings.json` lacks something the action needs, e.g. a collection.
+    Configuration(String),
+    /// Reading keys, drawing the screen or handling signals failed.
+    Terminal(String),
+    /// The database file at the path changed on disk since it was last read or written.
+    ExternalChange(String),
+}
 
 impl fmt::Display for AppError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "There is an error: {}", self.0)
+        match self {
+            AppError::Storage(message)
+            | AppError::Network(message)
+            | AppError::Parse(message)
+            | AppError::Validation(message)
+            | AppError::NotFound(message)
+            | AppError::Configuration(message)
+            | AppError::Terminal(message) => write!(f, "{}", message),
+            AppError::ExternalChange(path) => write!(f, "{} was changed outside of the app", path),
+        }
+    }
+}
+
+impl error::Error for AppError {}
+
+impl AppError {
+    /// A failed read or write of the file at `path`.
+    pub fn file(path: impl AsRef<Path>, error: io::Error) -> Self {
+        AppError::Storage(format!("{}: {}", path.as_ref().display(), describe(&error)))
+    }
+
+    /// A failed read from or write to the terminal.
+    pub fn terminal(error: io::Error) -> Self {
+        AppError::Terminal(format!("Terminal error: {}", error))
+    }
+}
+
+fn describe(error: &io::Error) -> String {
+    match error.kind() {
+        io::ErrorKind::NotFound => "file not found".to_string(),
+        io::ErrorKind::PermissionDenied => "permission denied".to_string(),
+        _ => error.to_string(),
     }
 }
 
-impl Error for AppError {}
+impl From<io::Error> for AppError {
+    fn from(error: io::Error) -> Self {
+        AppError::Storage(format!("File error: {}", describe(&error)))
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(error: serde_json::Error) -> Self {
+        match error.classify() {
+            serde_json::error::Category::Io => AppError::Storage(format!("File error: {}", error)),
+            _ => AppError::Parse(format!("Invalid data: {}", error)),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(error: reqwest::Error) -> Self {
+        match error.is_serialization() {
+            true => AppError::Parse(format!("Unexpected answer from the server: {}", error)),
+            false => AppError::Network(format!("Network error, check your connection: {}", error)),
+        }
+    }
+}
+
+impl From<image::ImageError> for AppError {
+    fn from(error: image::ImageError) -> Self {
+        match error {
+            image::ImageError::IoError(error) => error.into(),
+            error => AppError::Parse(format!("Invalid image: {}", error)),
+        }
+    }
+}
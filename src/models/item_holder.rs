@@ -1,111 +1,1104 @@
-use chrono::{serde::ts_seconds, DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use std::fmt;
 
-use super::{list::StatefulList, record::Record};
+use chrono::{
+    serde::ts_seconds, DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc,
+};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use strum::{EnumIter, IntoEnumIterator};
 
+use crate::{
+    borrowers::Borrowers,
+    valuation::{decimal_cents, parse_cents, Cents},
+};
+
+use super::{
+    annotations::Annotations,
+    error::{AppError, Result},
+    record::Record,
+};
+
+/// A record in the collection with the copies of it and what is noted about it.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct ItemHolder {
     pub record: Record,
     pub items: Vec<Item>,
-}
-
-pub struct StatefulItemHolder {
-    pub record: Record,
-    pub detail_offset: usize,
-    pub list: StatefulList<StatefulItem>,
+    #[serde(flatten)]
+    pub annotations: Annotations,
+    /// Personal rating from 1 to 5.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_rating"
+    )]
+    pub rating: Option<u8>,
+    /// Review and listening notes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
 }
 
 impl ItemHolder {
+    /// A record without copies.
     pub fn new(record: Record) -> Self {
         ItemHolder {
-            record: record,
+            record,
             items: vec![],
+            annotations: Annotations::default(),
+            rating: None,
+            notes: None,
         }
     }
 
+    /// A record with one new copy.
     pub fn new_with_item(record: Record) -> Self {
         ItemHolder {
-            record: record,
+            record,
             items: vec![Item::new_with_id(0)],
+            annotations: Annotations::default(),
+            rating: None,
+            notes: None,
         }
     }
 
+    /// Adds a new copy with the next free id.
     pub fn add_item(&mut self) {
-        self.items.push(Item::new_with_id(
-            self.items
-                .iter()
-                .map(|item| item.id)
-                .reduce(|accum, item| if accum >= item { accum } else { item })
-                .unwrap_or(0)
-                + 1,
-        ))
+        self.items.push(Item::new_with_id(self.next_item_id()))
+    }
+
+    /// The id after the highest one used by a copy of the record.
+    pub fn next_item_id(&self) -> i64 {
+        self.items
+            .iter()
+            .map(|item| item.id)
+            .reduce(|accum, item| if accum >= item { accum } else { item })
+            .unwrap_or(0)
+            + 1
+    }
+
+    /// Played events of every copy.
+    pub fn plays(&self) -> impl Iterator<Item = &ItemEvent> {
+        self.items.iter().flat_map(|i| i.plays())
+    }
+
+    /// Number of plays of all copies in the given year, in local time.
+    pub fn plays_in_year(&self, year: i32) -> usize {
+        self.plays()
+            .filter(|e| e.date.with_timezone(&Local).year() == year)
+            .count()
     }
 
-    pub fn to_stateful(self) -> StatefulItemHolder {
-        StatefulItemHolder {
-            record: self.record,
-            list: StatefulList::with_items(self.items.into_iter().map(Item::to_stateful).collect()),
-            detail_offset: 0,
+    /// Parses a 1 to 5 rating, an empty text clears it.
+    pub fn parse_rating(text: &str) -> Result<Option<u8>> {
+        match text.trim() {
+            "" => Ok(None),
+            rating => Ok(Some(
+                rating
+                    .parse()
+                    .ok()
+                    .filter(|r| (1..=5).contains(r))
+                    .ok_or_else(|| {
+                        AppError::Validation("Rating must be from 1 to 5".to_string())
+                    })?,
+            )),
         }
     }
 }
 
+/// Rejects ratings outside 1 to 5 in hand edited or merged files.
+fn deserialize_rating<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<u8>, D::Error> {
+    match Option::<u8>::deserialize(deserializer)? {
+        Some(rating) if !(1..=5).contains(&rating) => Err(de::Error::custom(format!(
+            "rating {} is not from 1 to 5",
+            rating
+        ))),
+        rating => Ok(rating),
+    }
+}
+
+/// A single copy of a record with its grades, whereabouts and history.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Item {
     pub id: i64,
     pub events: Vec<ItemEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_condition: Option<Grade>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sleeve_condition: Option<Grade>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acquisition: Option<Acquisition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    #[serde(flatten)]
+    pub annotations: Annotations,
+    /// Previous versions of edited or deleted events.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub audit: Vec<AuditEntry>,
+}
+
+/// How an event was changed after it was recorded.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum AuditAction {
+    Edited,
+    Deleted,
+}
+
+/// A change to the history of a copy, kept so edits and deletions can be traced.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct AuditEntry {
+    #[serde(with = "ts_seconds")]
+    pub date: DateTime<Utc>,
+    pub action: AuditAction,
+    /// The event as it was before the change.
+    pub event: ItemEvent,
+}
+
+/// Where a copy is shelved, from the broadest to the most specific place.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Location {
+    pub room: String,
+    pub shelf: String,
+    #[serde(rename = "crate")]
+    pub crate_: String,
+    pub slot: String,
+}
+
+impl Location {
+    /// Parses "room/shelf/crate/slot", trailing parts can be left out.
+    pub fn parse(text: &str) -> Result<Location> {
+        let parts: Vec<String> = text.split('/').map(|p| p.trim().to_string()).collect();
+        if parts.len() > 4 || parts[0].is_empty() {
+            Err(AppError::Validation(
+                "Expected \"room/shelf/crate/slot\"".to_string(),
+            ))?
+        }
+        let part = |i: usize| parts.get(i).cloned().unwrap_or_default();
+        Ok(Location {
+            room: part(0),
+            shelf: part(1),
+            crate_: part(2),
+            slot: part(3),
+        })
+    }
+
+    /// The room, shelf and crate without the slot.
+    pub fn container(&self) -> String {
+        [&self.room, &self.shelf, &self.crate_]
+            .iter()
+            .filter(|p| !p.is_empty())
+            .map(|p| p.as_str())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.slot.as_str() {
+            "" => write!(f, "{}", self.container()),
+            slot => write!(f, "{}/{}", self.container(), slot),
+        }
+    }
 }
 
+/// Where, when and for how much a copy was bought.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
-pub struct StatefulItem {
-    pub item: Item,
-    pub input: Option<ItemEventType>,
+pub struct Acquisition {
+    pub date: NaiveDate,
+    pub source: String,
+    #[serde(with = "decimal_cents")]
+    pub price: Cents,
+    pub currency: String,
+}
+
+impl Acquisition {
+    /// Parses "YYYY-MM-DD; seller; 12.50 EUR".
+    pub fn parse(text: &str) -> Result<Acquisition> {
+        let parts: Vec<&str> = text.split(';').map(|p| p.trim()).collect();
+        let (date, source, price) = match parts[..] {
+            [date, source, price] => (date, source, price),
+            _ => Err(AppError::Validation(
+                "Expected \"YYYY-MM-DD; seller; price currency\"".to_string(),
+            ))?,
+        };
+        let (price, currency) = price
+            .split_once(' ')
+            .ok_or_else(|| AppError::Validation("Price needs a currency".to_string()))?;
+        Ok(Acquisition {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| AppError::Validation(format!("Invalid date \"{}\"", date)))?,
+            source: source.to_string(),
+            price: parse_cents(price)?,
+            currency: currency.trim().to_uppercase(),
+        })
+    }
+}
+
+/// Who has a lent copy and until when, derived from its latest lend event.
+pub struct Loan<'a> {
+    pub event: &'a ItemEvent,
+    /// `None` for copies lent before borrowers were tracked.
+    pub borrower_id: Option<i64>,
+    pub due: Option<NaiveDate>,
+}
+
+impl Loan<'_> {
+    /// Loans are due in four weeks unless agreed otherwise.
+    pub fn default_due() -> NaiveDate {
+        Local::now().date_naive() + Duration::weeks(4)
+    }
+
+    /// Whether the copy should have been returned before `today`.
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.due.is_some_and(|due| due < today)
+    }
+}
+
+/// Who a copy is lent to and until when, as entered before lending it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoanTerms {
+    pub borrower: String,
+    pub due: NaiveDate,
+    pub message: Option<String>,
+}
+
+impl LoanTerms {
+    /// Parses "borrower; YYYY-MM-DD; message", the due date defaults to
+    /// [`Loan::default_due`] and the message is optional.
+    pub fn parse(text: &str) -> Result<LoanTerms> {
+        let mut parts = text.splitn(3, ';').map(|p| p.trim());
+        let borrower = parts.next().unwrap_or_default().to_string();
+        let due = match parts.next() {
+            None | Some("") => Loan::default_due(),
+            Some(due) => NaiveDate::parse_from_str(due, "%Y-%m-%d")
+                .map_err(|_| AppError::Validation(format!("Invalid due date \"{}\"", due)))?,
+        };
+        Ok(LoanTerms {
+            borrower,
+            due,
+            message: parts.next().filter(|m| !m.is_empty()).map(String::from),
+        })
+    }
 }
 
 impl Item {
+    /// A copy created now, without grades or location.
     pub fn new_with_id(id: i64) -> Self {
         Item {
-            id: id,
+            id,
             events: vec![ItemEvent::new(ItemEventType::Created)],
+            media_condition: None,
+            sleeve_condition: None,
+            acquisition: None,
+            location: None,
+            annotations: Annotations::default(),
+            audit: vec![],
+        }
+    }
+
+    /// The open loan of the copy, if its last lend event wasn't followed by a return.
+    pub fn loan(&self) -> Option<Loan<'_>> {
+        let event = self
+            .events
+            .iter()
+            .rev()
+            .find(|e| matches!(e.event_type, ItemEventType::Lent | ItemEventType::Returned))?;
+        if event.event_type != ItemEventType::Lent {
+            return None;
+        }
+        Some(match event.payload {
+            Some(EventPayload::Loan { borrower_id, due }) => Loan {
+                event,
+                borrower_id: Some(borrower_id),
+                due: Some(due),
+            },
+            _ => Loan {
+                event,
+                borrower_id: None,
+                due: None,
+            },
+        })
+    }
+
+    /// Adds a note or a care event, the other kinds are added by their own actions.
+    pub fn add_event(&mut self, event_type: ItemEventType, message: Option<String>) -> Result<()> {
+        if !event_type.can_be_added() {
+            Err(AppError::Validation(format!(
+                "{} events can't be added directly",
+                event_type.label()
+            )))?
+        }
+        if !self.is_in_collection() && event_type != ItemEventType::Message {
+            Err(AppError::Validation(format!("Item is {}", self.state())))?
+        }
+        self.events.push(ItemEvent {
+            message,
+            ..ItemEvent::new(event_type)
+        });
+        Ok(())
+    }
+
+    /// Lends the copy to the borrower called `name`, registering them if needed.
+    pub fn lend(
+        &mut self,
+        borrowers: &mut Borrowers,
+        name: &str,
+        due: NaiveDate,
+        message: Option<String>,
+    ) -> Result<()> {
+        if self.state() != ItemState::InCollection {
+            Err(AppError::Validation(format!("Item is {}", self.state())))?
+        }
+        let borrower_id = borrowers.find_or_add(name)?;
+        self.events.push(ItemEvent::with_payload(
+            ItemEventType::Lent,
+            message,
+            EventPayload::Loan { borrower_id, due },
+        ));
+        Ok(())
+    }
+
+    /// Ends the open loan of the copy.
+    pub fn return_loan(&mut self, borrowers: &Borrowers) -> Result<()> {
+        let borrower_id = self
+            .loan()
+            .ok_or_else(|| AppError::Validation("Item is not lent".to_string()))?
+            .borrower_id;
+        self.events.push(ItemEvent::with_message(
+            ItemEventType::Returned,
+            format!("Returned by {}", borrowers.name(borrower_id)),
+        ));
+        Ok(())
+    }
+
+    /// Records that the copy left the collection with a sale or trade event, see
+    /// [`ItemEvent::sale`] and [`ItemEvent::trade`].
+    pub fn part_with(&mut self, event: ItemEvent) -> Result<()> {
+        if self.state() != ItemState::InCollection {
+            Err(AppError::Validation(format!("Item is {}", self.state())))?
+        }
+        self.events.push(event);
+        Ok(())
+    }
+
+    /// Stores the copy at `location` and logs where it came from.
+    pub fn move_to(&mut self, location: Location) {
+        let message = match &self.location {
+            Some(from) => format!("{} -> {}", from, location),
+            None => format!("-> {}", location),
+        };
+        self.location = Some(location);
+        self.events
+            .push(ItemEvent::with_message(ItemEventType::Moved, message));
+    }
+
+    /// Updates the grades and logs the change, `None` keeps the current grade.
+    pub fn grade(&mut self, media: Option<Grade>, sleeve: Option<Grade>) {
+        let mut changes = vec![];
+        if let Some(media) = media.filter(|m| Some(*m) != self.media_condition) {
+            changes.push(format!(
+                "Media {} -> {}",
+                grade_code(self.media_condition),
+                media
+            ));
+            self.media_condition = Some(media);
+        }
+        if let Some(sleeve) = sleeve.filter(|s| Some(*s) != self.sleeve_condition) {
+            changes.push(format!(
+                "Sleeve {} -> {}",
+                grade_code(self.sleeve_condition),
+                sleeve
+            ));
+            self.sleeve_condition = Some(sleeve);
+        }
+        if !changes.is_empty() {
+            self.events.push(ItemEvent::with_message(
+                ItemEventType::Graded,
+                changes.join(", "),
+            ));
+        }
+    }
+
+    /// The play events of the copy.
+    pub fn plays(&self) -> impl Iterator<Item = &ItemEvent> {
+        self.events
+            .iter()
+            .filter(|e| e.event_type == ItemEventType::Played)
+    }
+
+    /// Replays the event log, later events override earlier ones.
+    pub fn state(&self) -> ItemState {
+        self.events
+            .iter()
+            .fold(ItemState::InCollection, |state, e| {
+                e.event_type.apply(state)
+            })
+    }
+
+    /// Whether copies are only lent while in the collection and only returned while on loan.
+    fn loans_in_order(events: &[ItemEvent]) -> bool {
+        let mut state = ItemState::InCollection;
+        for event in events {
+            match event.event_type {
+                ItemEventType::Lent if state != ItemState::InCollection => return false,
+                ItemEventType::Returned if state != ItemState::OnLoan => return false,
+                _ => {}
+            }
+            state = event.event_type.apply(state);
         }
+        true
     }
-    pub fn to_stateful(self) -> StatefulItem {
-        StatefulItem {
-            item: self,
-            input: None,
+
+    /// Replaces the events with `events` if the loans still add up.
+    fn set_events(&mut self, mut events: Vec<ItemEvent>) -> Result<()> {
+        events.sort_by_key(|e| e.date);
+        if !Item::loans_in_order(&events) {
+            Err(AppError::Validation(
+                "Lent and returned events would be out of order".to_string(),
+            ))?
         }
+        self.events = events;
+        Ok(())
+    }
+
+    /// Sold, traded, lost or transferred copies stay in the history but no longer count.
+    pub fn is_in_collection(&self) -> bool {
+        matches!(self.state(), ItemState::InCollection | ItemState::OnLoan)
+    }
+
+    /// Replaces the event at `index` with `event`, keeping the old version in the audit
+    /// trail. Returns the new index of the event once the history is sorted again.
+    pub fn edit_event(&mut self, index: usize, event: ItemEvent) -> Result<usize> {
+        let old = self
+            .events
+            .get(index)
+            .ok_or_else(|| AppError::NotFound("Event not found".to_string()))?;
+        if old.event_type == ItemEventType::Created || event.event_type == ItemEventType::Created {
+            Err(AppError::Validation(
+                "Created events can't be changed".to_string(),
+            ))?
+        }
+        if *old == event {
+            return Ok(index);
+        }
+        if old.event_type != event.event_type
+            && !(old.event_type.can_be_added() && event.event_type.can_be_added())
+        {
+            Err(AppError::Validation(format!(
+                "{} events can't be changed to {}",
+                old.event_type.label(),
+                event.event_type.label()
+            )))?
+        }
+        let old = old.clone();
+        let mut events = self.events.clone();
+        events[index] = event.clone();
+        self.set_events(events)?;
+        self.audit.push(AuditEntry {
+            date: Utc::now(),
+            action: AuditAction::Edited,
+            event: old,
+        });
+        Ok(self
+            .events
+            .iter()
+            .position(|e| *e == event)
+            .unwrap_or(index))
+    }
+
+    /// Removes the event at `index`, keeping it in the audit trail.
+    pub fn delete_event(&mut self, index: usize) -> Result<()> {
+        match self.events.get(index) {
+            None => Err(AppError::NotFound("Event not found".to_string()))?,
+            Some(e) if e.event_type == ItemEventType::Created => Err(AppError::Validation(
+                "Created events can't be deleted".to_string(),
+            ))?,
+            Some(_) => {}
+        }
+        let mut events = self.events.clone();
+        let event = events.remove(index);
+        self.set_events(events)?;
+        self.audit.push(AuditEntry {
+            date: Utc::now(),
+            action: AuditAction::Deleted,
+            event,
+        });
+        Ok(())
     }
 }
 
+/// Something that happened to a copy, dates are stored to the second.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct ItemEvent {
     #[serde(with = "ts_seconds")]
     pub date: DateTime<Utc>,
     pub event_type: ItemEventType,
     pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<EventPayload>,
+}
+
+/// Structured data attached to some event types.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum EventPayload {
+    Loan {
+        borrower_id: i64,
+        due: NaiveDate,
+    },
+    Sale {
+        #[serde(with = "decimal_cents")]
+        price: Cents,
+        currency: String,
+        buyer: String,
+    },
+    Trade {
+        counterparty: String,
+        received: String,
+    },
 }
 
 impl ItemEvent {
+    /// An event happening now.
     pub fn new(event_type: ItemEventType) -> Self {
         ItemEvent {
             date: Utc::now(),
-            event_type: event_type,
+            event_type,
             message: None,
+            payload: None,
         }
     }
 
+    /// An event happening now with a message.
     pub fn with_message(event_type: ItemEventType, message: String) -> Self {
         ItemEvent {
             date: Utc::now(),
-            event_type: event_type,
+            event_type,
             message: Some(message),
+            payload: None,
+        }
+    }
+
+    /// The editable parts of the event as "YYYY-MM-DD HH:MM; type; message" in local time.
+    pub fn to_edit_text(&self) -> String {
+        format!(
+            "{}; {}; {}",
+            self.date.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            self.event_type.label(),
+            self.message.as_deref().unwrap_or_default()
+        )
+    }
+
+    /// Applies text in the `to_edit_text` format, the payload is dropped when the type changes.
+    pub fn edited(&self, text: &str) -> Result<ItemEvent> {
+        let mut parts = text.splitn(3, ';').map(|p| p.trim());
+        let date = parts.next().unwrap_or_default();
+        let date = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M")
+            .ok()
+            .and_then(|d| Local.from_local_datetime(&d).single())
+            .ok_or_else(|| AppError::Validation(format!("Invalid date \"{}\"", date)))?;
+        let event_type = parts.next().unwrap_or_default();
+        let event_type = ItemEventType::parse(event_type).ok_or_else(|| {
+            AppError::Validation(format!("Unknown event type \"{}\"", event_type))
+        })?;
+        Ok(ItemEvent {
+            date: date.with_timezone(&Utc),
+            event_type,
+            message: parts.next().filter(|m| !m.is_empty()).map(String::from),
+            payload: self
+                .payload
+                .clone()
+                .filter(|_| event_type == self.event_type),
+        })
+    }
+
+    /// An event happening now with structured data.
+    pub fn with_payload(
+        event_type: ItemEventType,
+        message: Option<String>,
+        payload: EventPayload,
+    ) -> Self {
+        ItemEvent {
+            date: Utc::now(),
+            event_type,
+            message,
+            payload: Some(payload),
         }
     }
+
+    /// A sale from "price currency; buyer; message", the buyer and message are optional.
+    pub fn sale(text: &str) -> Result<ItemEvent> {
+        let mut parts = text.splitn(3, ';').map(|p| p.trim());
+        let (price, currency) = parts
+            .next()
+            .unwrap_or_default()
+            .split_once(' ')
+            .ok_or_else(|| {
+                AppError::Validation("Expected \"price currency; buyer; message\"".to_string())
+            })?;
+        let payload = EventPayload::Sale {
+            price: parse_cents(price)?,
+            currency: currency.trim().to_uppercase(),
+            buyer: parts.next().unwrap_or_default().to_string(),
+        };
+        let message = parts.next().filter(|m| !m.is_empty()).map(String::from);
+        Ok(ItemEvent::with_payload(
+            ItemEventType::Sold,
+            message,
+            payload,
+        ))
+    }
+
+    /// A trade from "counterparty; received".
+    pub fn trade(text: &str) -> Result<ItemEvent> {
+        let (counterparty, received) = text.split_once(';').ok_or_else(|| {
+            AppError::Validation("Expected \"counterparty; received\"".to_string())
+        })?;
+        let payload = EventPayload::Trade {
+            counterparty: counterparty.trim().to_string(),
+            received: received.trim().to_string(),
+        };
+        Ok(ItemEvent::with_payload(
+            ItemEventType::Traded,
+            None,
+            payload,
+        ))
+    }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+/// Kinds of events, stored by their variant name.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, EnumIter)]
 pub enum ItemEventType {
     Created,
     Message,
     Lent,
+    Returned,
+    Graded,
+    Moved,
+    TransferredIn,
+    TransferredOut,
+    Sold,
+    Traded,
+    Cleaned,
+    Played,
+    Repaired,
+    Lost,
+}
+
+impl ItemEventType {
+    /// Name of the event type as shown to and typed by the user.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ItemEventType::Created => "Created",
+            ItemEventType::Message => "Note",
+            ItemEventType::Lent => "Lent",
+            ItemEventType::Returned => "Returned",
+            ItemEventType::Graded => "Graded",
+            ItemEventType::Moved => "Moved",
+            ItemEventType::TransferredIn => "Transferred in",
+            ItemEventType::TransferredOut => "Transferred out",
+            ItemEventType::Sold => "Sold",
+            ItemEventType::Traded => "Traded",
+            ItemEventType::Cleaned => "Cleaned",
+            ItemEventType::Played => "Played",
+            ItemEventType::Repaired => "Repaired",
+            ItemEventType::Lost => "Lost",
+        }
+    }
+
+    /// Notes and care events, which carry no data and can be added or retyped freely.
+    pub fn can_be_added(&self) -> bool {
+        matches!(
+            self,
+            ItemEventType::Message
+                | ItemEventType::Cleaned
+                | ItemEventType::Played
+                | ItemEventType::Repaired
+                | ItemEventType::Lost
+        )
+    }
+
+    /// The state of a copy after an event of this type happened in `state`.
+    fn apply(&self, state: ItemState) -> ItemState {
+        match self {
+            ItemEventType::Created | ItemEventType::TransferredIn | ItemEventType::Returned => {
+                ItemState::InCollection
+            }
+            ItemEventType::Lent => ItemState::OnLoan,
+            ItemEventType::Sold => ItemState::Sold,
+            ItemEventType::Traded => ItemState::Traded,
+            ItemEventType::Lost => ItemState::Lost,
+            ItemEventType::TransferredOut => ItemState::TransferredOut,
+            _ => state,
+        }
+    }
+
+    /// The event type with the given label, ignoring case.
+    pub fn parse(label: &str) -> Option<ItemEventType> {
+        ItemEventType::iter().find(|t| t.label().eq_ignore_ascii_case(label.trim()))
+    }
+}
+
+/// Where a copy currently is, derived from its events.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemState {
+    InCollection,
+    OnLoan,
+    Sold,
+    Traded,
+    Lost,
+    TransferredOut,
+}
+
+impl fmt::Display for ItemState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ItemState::InCollection => "in collection",
+            ItemState::OnLoan => "on loan",
+            ItemState::Sold => "sold",
+            ItemState::Traded => "traded",
+            ItemState::Lost => "lost",
+            ItemState::TransferredOut => "transferred",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Goldmine grading scale, declared from worst to best so better grades compare greater.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, EnumIter)]
+pub enum Grade {
+    #[serde(rename = "P")]
+    Poor,
+    #[serde(rename = "F")]
+    Fair,
+    #[serde(rename = "G")]
+    Good,
+    #[serde(rename = "G+")]
+    GoodPlus,
+    #[serde(rename = "VG")]
+    VeryGood,
+    #[serde(rename = "VG+")]
+    VeryGoodPlus,
+    #[serde(rename = "NM")]
+    NearMint,
+    #[serde(rename = "M")]
+    Mint,
+}
+
+impl Grade {
+    /// Short Goldmine code, e.g. "VG+".
+    pub fn code(&self) -> &'static str {
+        match self {
+            Grade::Poor => "P",
+            Grade::Fair => "F",
+            Grade::Good => "G",
+            Grade::GoodPlus => "G+",
+            Grade::VeryGood => "VG",
+            Grade::VeryGoodPlus => "VG+",
+            Grade::NearMint => "NM",
+            Grade::Mint => "M",
+        }
+    }
+
+    /// Condition names used by the Discogs marketplace.
+    pub fn discogs_name(&self) -> &'static str {
+        match self {
+            Grade::Poor => "Poor (P)",
+            Grade::Fair => "Fair (F)",
+            Grade::Good => "Good (G)",
+            Grade::GoodPlus => "Good Plus (G+)",
+            Grade::VeryGood => "Very Good (VG)",
+            Grade::VeryGoodPlus => "Very Good Plus (VG+)",
+            Grade::NearMint => "Near Mint (NM or M-)",
+            Grade::Mint => "Mint (M)",
+        }
+    }
+
+    /// The grade with the given code, ignoring case.
+    pub fn parse(code: &str) -> Option<Grade> {
+        Grade::iter().find(|g| g.code().eq_ignore_ascii_case(code.trim()))
+    }
+}
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// The code of a grade, "-" for copies not graded yet.
+pub fn grade_code(grade: Option<Grade>) -> &'static str {
+    grade.map_or("-", |g| g.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratings_are_from_one_to_five() {
+        assert_eq!(ItemHolder::parse_rating(" 4 ").unwrap(), Some(4));
+        assert_eq!(ItemHolder::parse_rating("").unwrap(), None);
+        assert!(ItemHolder::parse_rating("0").is_err());
+        assert!(ItemHolder::parse_rating("6").is_err());
+        assert!(ItemHolder::parse_rating("four").is_err());
+    }
+
+    #[test]
+    fn ratings_above_five_are_not_loaded() {
+        let mut json = serde_json::to_value(ItemHolder::new(Record::default())).unwrap();
+        json["rating"] = 3.into();
+        let holder: ItemHolder = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(holder.rating, Some(3));
+        json["rating"] = 200.into();
+        assert!(serde_json::from_value::<ItemHolder>(json).is_err());
+    }
+
+    #[test]
+    fn plays_count_per_year() {
+        let mut holder = ItemHolder::new_with_item(Record::default());
+        holder.add_item();
+        let played = |date: &str| ItemEvent {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .unwrap()
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_local_timezone(Local)
+                .unwrap()
+                .with_timezone(&Utc),
+            ..ItemEvent::new(ItemEventType::Played)
+        };
+        holder.items[0].events.push(played("2020-03-01"));
+        holder.items[0].events.push(played("2021-03-01"));
+        holder.items[1].events.push(played("2021-06-01"));
+        assert_eq!(holder.plays().count(), 3);
+        assert_eq!(holder.plays_in_year(2021), 2);
+        assert_eq!(holder.plays_in_year(2022), 0);
+    }
+
+    fn event_at(event_type: ItemEventType, day: u32) -> ItemEvent {
+        ItemEvent {
+            date: Utc.with_ymd_and_hms(2023, 5, day, 12, 0, 0).unwrap(),
+            ..ItemEvent::new(event_type)
+        }
+    }
+
+    /// A copy created on the 1st, noted on the 2nd, lent on the 3rd and returned on the 5th.
+    fn lent_and_returned() -> Item {
+        let mut item = Item::new_with_id(1);
+        item.events = vec![
+            event_at(ItemEventType::Created, 1),
+            event_at(ItemEventType::Message, 2),
+            ItemEvent {
+                payload: Some(EventPayload::Loan {
+                    borrower_id: 1,
+                    due: NaiveDate::from_ymd_opt(2023, 6, 1).unwrap(),
+                }),
+                ..event_at(ItemEventType::Lent, 3)
+            },
+            event_at(ItemEventType::Returned, 5),
+        ];
+        item
+    }
+
+    #[test]
+    fn edits_are_kept_in_the_audit_trail() {
+        let mut item = lent_and_returned();
+        let note = item.events[1].clone();
+        let played = note.edited("2023-05-06 12:00; played; Side A").unwrap();
+        assert_eq!(item.edit_event(1, played.clone()).unwrap(), 3);
+        assert_eq!(item.events[3], played);
+        assert_eq!(item.audit.len(), 1);
+        assert_eq!(item.audit[0].action, AuditAction::Edited);
+        assert_eq!(item.audit[0].event, note);
+
+        item.delete_event(3).unwrap();
+        assert_eq!(item.events.len(), 3);
+        assert_eq!(item.audit[1].action, AuditAction::Deleted);
+        assert_eq!(item.audit[1].event, played);
+        assert!(item.delete_event(0).is_err());
+    }
+
+    #[test]
+    fn edits_cant_make_up_loans_or_sales() {
+        let mut item = lent_and_returned();
+        for event_type in [
+            ItemEventType::Lent,
+            ItemEventType::Sold,
+            ItemEventType::Returned,
+        ] {
+            let event = ItemEvent {
+                event_type,
+                ..item.events[1].clone()
+            };
+            assert!(item.edit_event(1, event).is_err());
+        }
+        let note = ItemEvent {
+            payload: None,
+            ..event_at(ItemEventType::Message, 3)
+        };
+        assert!(item.edit_event(2, note).is_err());
+        assert_eq!(item, lent_and_returned());
+    }
+
+    #[test]
+    fn edits_keep_loans_in_order() {
+        let mut item = lent_and_returned();
+        let late = ItemEvent {
+            date: Utc.with_ymd_and_hms(2023, 5, 7, 12, 0, 0).unwrap(),
+            ..item.events[2].clone()
+        };
+        assert!(item.edit_event(2, late).is_err());
+        assert!(item.delete_event(2).is_err());
+        assert_eq!(item, lent_and_returned());
+
+        let earlier = ItemEvent {
+            date: Utc.with_ymd_and_hms(2023, 5, 4, 12, 0, 0).unwrap(),
+            ..item.events[3].clone()
+        };
+        assert_eq!(item.edit_event(3, earlier).unwrap(), 3);
+        assert_eq!(item.state(), ItemState::InCollection);
+    }
+
+    #[test]
+    fn grades_are_read_and_logged() {
+        assert_eq!(Grade::parse(" vg+ "), Some(Grade::VeryGoodPlus));
+        assert_eq!(Grade::parse("M-"), None);
+        assert!(Grade::NearMint > Grade::VeryGoodPlus);
+
+        let mut item = Item::new_with_id(1);
+        item.grade(Some(Grade::VeryGoodPlus), None);
+        item.grade(Some(Grade::NearMint), Some(Grade::VeryGood));
+        item.grade(Some(Grade::NearMint), None);
+        assert_eq!(item.media_condition, Some(Grade::NearMint));
+        assert_eq!(item.sleeve_condition, Some(Grade::VeryGood));
+        let messages: Vec<_> = item
+            .events
+            .iter()
+            .filter(|e| e.event_type == ItemEventType::Graded)
+            .map(|e| e.message.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            messages,
+            vec!["Media - -> VG+", "Media VG+ -> NM, Sleeve - -> VG"]
+        );
+    }
+
+    #[test]
+    fn locations_are_read_from_the_room_down() {
+        let location = Location::parse(" Living / A / 3 / 12 ").unwrap();
+        assert_eq!(location.container(), "Living/A/3");
+        assert_eq!(location.to_string(), "Living/A/3/12");
+        let room = Location::parse("Attic").unwrap();
+        assert_eq!(room.shelf, "");
+        assert_eq!(room.to_string(), "Attic");
+        assert!(Location::parse("").is_err());
+        assert!(Location::parse("/A").is_err());
+        assert!(Location::parse("a/b/c/d/e").is_err());
+
+        let mut item = Item::new_with_id(1);
+        item.move_to(room);
+        item.move_to(location);
+        assert_eq!(
+            item.events.last().unwrap().message.as_deref(),
+            Some("Attic -> Living/A/3/12")
+        );
+    }
+
+    #[test]
+    fn purchases_are_read_and_stored_in_cents() {
+        let acquisition = Acquisition::parse(" 2020-01-02 ; Shop ; 12.5 eur").unwrap();
+        assert_eq!(
+            acquisition,
+            Acquisition {
+                date: NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+                source: "Shop".to_string(),
+                price: 1250,
+                currency: "EUR".to_string(),
+            }
+        );
+        let json = serde_json::to_string(&acquisition).unwrap();
+        assert!(json.contains("\"price\":12.5"));
+        assert_eq!(
+            serde_json::from_str::<Acquisition>(&json).unwrap(),
+            acquisition
+        );
+
+        assert!(Acquisition::parse("2020-01-02; Shop").is_err());
+        assert!(Acquisition::parse("2020-01-02; Shop; 12.50").is_err());
+        assert!(Acquisition::parse("02.01.2020; Shop; 12.50 EUR").is_err());
+        assert!(Acquisition::parse("2020-01-02; Shop; cheap EUR").is_err());
+    }
+
+    #[test]
+    fn sales_and_trades_are_read_from_the_input() {
+        let sale = ItemEvent::sale("12.50 eur; Bob; Record fair").unwrap();
+        assert_eq!(sale.event_type, ItemEventType::Sold);
+        assert_eq!(sale.message.as_deref(), Some("Record fair"));
+        assert_eq!(
+            sale.payload,
+            Some(EventPayload::Sale {
+                price: 1250,
+                currency: "EUR".to_string(),
+                buyer: "Bob".to_string(),
+            })
+        );
+        let sale = ItemEvent::sale("3 USD").unwrap();
+        assert_eq!(sale.message, None);
+        assert!(matches!(sale.payload, Some(EventPayload::Sale { buyer, .. }) if buyer.is_empty()));
+        assert!(ItemEvent::sale("12.50").is_err());
+        assert!(ItemEvent::sale("free USD; Bob").is_err());
+
+        let trade = ItemEvent::trade(" Alice ; Low (2nd press) ").unwrap();
+        assert_eq!(trade.event_type, ItemEventType::Traded);
+        assert_eq!(
+            trade.payload,
+            Some(EventPayload::Trade {
+                counterparty: "Alice".to_string(),
+                received: "Low (2nd press)".to_string(),
+            })
+        );
+        assert!(ItemEvent::trade("Alice").is_err());
+    }
+
+    #[test]
+    fn loan_terms_default_to_four_weeks() {
+        let terms = LoanTerms::parse(" Bob ").unwrap();
+        assert_eq!(terms.borrower, "Bob");
+        assert_eq!(terms.due, Loan::default_due());
+        assert_eq!(terms.message, None);
+        let terms = LoanTerms::parse("Bob; 2030-05-01; Careful with it").unwrap();
+        assert_eq!(terms.due, NaiveDate::from_ymd_opt(2030, 5, 1).unwrap());
+        assert_eq!(terms.message.as_deref(), Some("Careful with it"));
+        assert_eq!(
+            LoanTerms::parse("Bob; ; x").unwrap().due,
+            Loan::default_due()
+        );
+        assert!(LoanTerms::parse("Bob; next week").is_err());
+    }
+
+    #[test]
+    fn parted_copies_only_take_notes() {
+        let mut item = Item::new_with_id(1);
+        assert!(item.add_event(ItemEventType::Sold, None).is_err());
+        item.add_event(ItemEventType::Cleaned, None).unwrap();
+        assert_eq!(item.state(), ItemState::InCollection);
+
+        item.part_with(ItemEvent::sale("5 EUR").unwrap()).unwrap();
+        assert_eq!(item.state(), ItemState::Sold);
+        assert!(!item.is_in_collection());
+        assert!(item
+            .part_with(ItemEvent::trade("Alice; Low").unwrap())
+            .is_err());
+        assert!(item.add_event(ItemEventType::Played, None).is_err());
+        item.add_event(ItemEventType::Message, Some("Sold at the fair".to_string()))
+            .unwrap();
+        assert_eq!(item.state(), ItemState::Sold);
+
+        let mut lost = Item::new_with_id(2);
+        lost.add_event(ItemEventType::Lost, None).unwrap();
+        assert_eq!(lost.state(), ItemState::Lost);
+        assert!(lost
+            .part_with(ItemEvent::trade("Alice; Low").unwrap())
+            .is_err());
+    }
 }
@@ -1,7 +1,14 @@
-pub mod app;
+//! Data types of the collection as stored in the database file.
+
+/// Tags and custom fields.
+pub mod annotations;
+/// The error type shared by the whole crate.
 pub mod error;
+/// Records in the collection, their copies and the events of those.
 pub mod item_holder;
-pub mod list;
+/// Results of a Discogs database search.
 pub mod query;
+/// Release metadata as Discogs describes it.
 pub mod record;
+/// Contents of `settings.json`.
 pub mod settings;
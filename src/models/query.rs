@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+/// A release found by a Discogs search.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct DiscogsSearchResultRelease {
     pub id: i64,
@@ -9,6 +10,7 @@ pub struct DiscogsSearchResultRelease {
     pub format: Vec<String>,
 }
 
+/// A search result that isn't a release, e.g. an artist or label.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DiscogsSearchResultOther {
     id: i64,
@@ -16,6 +18,7 @@ pub struct DiscogsSearchResultOther {
     type_: String,
 }
 
+/// Any kind of search result.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 pub enum DiscogsSearchResult {
@@ -23,6 +26,7 @@ pub enum DiscogsSearchResult {
     DiscogsSearchResultOther(DiscogsSearchResultOther),
 }
 
+/// A page of search results.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DiscogsSearchResponse {
     // pagination: String,
@@ -30,6 +34,7 @@ pub struct DiscogsSearchResponse {
 }
 
 impl DiscogsSearchResponse {
+    /// Only the releases among the results.
     pub fn get_releases(self) -> Vec<DiscogsSearchResultRelease> {
         self.results
             .into_iter()
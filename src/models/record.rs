@@ -1,15 +1,23 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+/// A medium of the release, e.g. two LPs.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct Format {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qty: Option<String>,
+    /// E.g. "LP", "Album", "Reissue".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub descriptions: Vec<String>,
 }
 
+/// A label that released the record with its catalog number.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Label {
     pub name: String,
     pub catno: String,
 }
+/// A barcode, matrix or other identifier, e.g. "Barcode" and its digits.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Identifier {
     #[serde(rename = "type")]
@@ -17,13 +25,53 @@ pub struct Identifier {
     pub value: String,
 }
 
+/// An artist credited on the release or a track. The id is 0 for artists
+/// entered by hand.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Artist {
     pub name: String,
     pub role: String,
     pub id: i64,
+    /// Tracks a release level credit applies to, empty for all of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracks: Option<String>,
+}
+
+/// Pressing plants, distributors, studios and the like.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Company {
+    pub name: String,
+    #[serde(default)]
+    pub entity_type_name: String,
+    #[serde(default)]
+    pub catno: String,
+}
+
+/// An image of the release on Discogs.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Image {
+    /// "primary" or "secondary".
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub uri: String,
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
 }
 
+/// A video linked to the release.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Video {
+    pub uri: String,
+    pub title: String,
+    /// Length in seconds.
+    #[serde(default)]
+    pub duration: i64,
+}
+
+/// A tracklist entry. `type_` is "track", or "heading" and "index" for lines
+/// that group other tracks.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Track {
     pub position: String,
@@ -34,7 +82,8 @@ pub struct Track {
     pub extraartists: Option<Vec<Artist>>,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+/// A release, from Discogs or entered by hand with a negative id.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct Record {
     pub id: i64,
     pub year: i32,
@@ -46,4 +95,31 @@ pub struct Record {
     pub genres: Vec<String>,
     pub styles: Vec<String>,
     pub tracklist: Vec<Track>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    /// Release date as given by Discogs, unknown parts are zeroed, e.g. "1977-00-00".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_quality: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<Image>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub videos: Vec<Video>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub companies: Vec<Company>,
+    /// Release level credits.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extraartists: Vec<Artist>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub series: Vec<Label>,
+}
+
+impl Record {
+    /// Records entered by hand use negative ids so they never collide with Discogs releases.
+    pub fn is_local(&self) -> bool {
+        self.id < 0
+    }
 }
@@ -1,7 +1,90 @@
+use std::fs;
+
 use serde::{Deserialize, Serialize};
 
+use crate::covers::GraphicsProtocol;
+
+use super::{
+    annotations::CustomField,
+    error::{AppError, Result},
+};
+
+/// Configuration read from `settings.json`.
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Settings {
     pub discogs_key: String,
+    /// Single collection setup, used when `collections` is empty.
+    #[serde(default)]
+    pub database_path: Option<String>,
+    #[serde(default)]
+    pub collections: Vec<CollectionSettings>,
+    /// Periodically check the database file for changes made outside of the app.
+    #[serde(default)]
+    pub watch_database: bool,
+    /// Currency of marketplace prices, defaults to the one of the Discogs account.
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default = "default_price_cache_path")]
+    pub price_cache_path: String,
+    #[serde(default = "default_borrowers_path")]
+    pub borrowers_path: String,
+    /// Schema of the custom fields that can be set on records and copies.
+    #[serde(default)]
+    pub custom_fields: Vec<CustomField>,
+    /// Directory of the downloaded cover images.
+    #[serde(default = "default_covers_path")]
+    pub covers_path: String,
+    /// How covers are drawn, detected from the terminal when not set.
+    #[serde(default)]
+    pub cover_protocol: Option<GraphicsProtocol>,
+    /// Bearer token clients of the `serve` API have to send.
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// Where reports of crashes of the app are appended.
+    #[serde(default = "default_crash_log_path")]
+    pub crash_log_path: String,
+}
+
+fn default_price_cache_path() -> String {
+    "price_cache.json".to_string()
+}
+
+/// A named collection and its database file.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CollectionSettings {
+    pub name: String,
     pub database_path: String,
 }
+
+impl Settings {
+    /// Reads `settings.json` from the working directory.
+    pub fn load() -> Result<Settings> {
+        let string =
+            fs::read_to_string("settings.json").map_err(|e| AppError::file("settings.json", e))?;
+        serde_json::from_str::<Settings>(&string)
+            .map_err(|e| AppError::Parse(format!("settings.json is invalid: {}", e)))
+    }
+
+    /// The configured collections, the single `database_path` one for older settings.
+    pub fn collections(&self) -> Vec<CollectionSettings> {
+        match (&self.collections[..], &self.database_path) {
+            ([], Some(path)) => vec![CollectionSettings {
+                name: "Collection".to_string(),
+                database_path: path.clone(),
+            }],
+            _ => self.collections.clone(),
+        }
+    }
+}
+
+fn default_borrowers_path() -> String {
+    "borrowers.json".to_string()
+}
+
+fn default_covers_path() -> String {
+    "covers".to_string()
+}
+
+fn default_crash_log_path() -> String {
+    "crash.log".to_string()
+}
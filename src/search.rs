@@ -0,0 +1,354 @@
+//! The local search syntax.
+
+use std::cmp::Reverse;
+
+use chrono::{Datelike, Local};
+
+use crate::models::{
+    annotations::{Annotations, CustomField},
+    item_holder::{Grade, Item, ItemHolder},
+};
+
+/// How a filter compares, `:` for equal, `>=` and `<=`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    AtLeast,
+    AtMost,
+}
+
+impl Comparison {
+    fn matches<T: Ord>(&self, value: T, expected: T) -> bool {
+        match self {
+            Comparison::Equal => value == expected,
+            Comparison::AtLeast => value >= expected,
+            Comparison::AtMost => value <= expected,
+        }
+    }
+
+    /// Compares numbers numerically, anything else case insensitively as text.
+    fn matches_value(&self, value: &str, expected: &str) -> bool {
+        match (value.parse::<f64>(), expected.parse::<f64>()) {
+            (Ok(value), Ok(expected)) => match self {
+                Comparison::Equal => value == expected,
+                Comparison::AtLeast => value >= expected,
+                Comparison::AtMost => value <= expected,
+            },
+            _ => self.matches(value.to_lowercase(), expected.to_lowercase()),
+        }
+    }
+}
+
+/// Filters on a single copy, a holder matches when one of its items passes all of them.
+/// Tags and custom fields of the holder count for each of its copies.
+pub enum ItemFilter {
+    Media(Comparison, Grade),
+    Sleeve(Comparison, Grade),
+    /// Copies stored in the given place or anywhere below it.
+    Location(String),
+    Tag(String),
+    /// A custom field declared in the settings.
+    Field(String, Comparison, String),
+}
+
+impl ItemFilter {
+    fn parse(token: &str, custom_fields: &[CustomField]) -> Option<ItemFilter> {
+        let (key, comparison, value) = split_filter(token)?;
+        match key.as_str() {
+            "media" => Some(ItemFilter::Media(comparison, Grade::parse(value)?)),
+            "sleeve" => Some(ItemFilter::Sleeve(comparison, Grade::parse(value)?)),
+            "loc" | "location" if comparison == Comparison::Equal => Some(ItemFilter::Location(
+                value.trim_end_matches('/').to_lowercase(),
+            )),
+            "tag" if comparison == Comparison::Equal => Some(ItemFilter::Tag(value.to_string())),
+            key if !value.is_empty() => custom_fields
+                .iter()
+                .find(|f| f.name.eq_ignore_ascii_case(key))
+                .map(|f| ItemFilter::Field(f.name.clone(), comparison, value.to_string())),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, holder: &Annotations, item: &Item) -> bool {
+        match self {
+            ItemFilter::Media(comparison, grade) => item
+                .media_condition
+                .is_some_and(|g| comparison.matches(g, *grade)),
+            ItemFilter::Sleeve(comparison, grade) => item
+                .sleeve_condition
+                .is_some_and(|g| comparison.matches(g, *grade)),
+            ItemFilter::Location(place) => item.location.as_ref().is_some_and(|l| {
+                let location = l.to_string().to_lowercase();
+                location == *place || location.starts_with(&(place.clone() + "/"))
+            }),
+            ItemFilter::Tag(tag) => holder.has_tag(tag) || item.annotations.has_tag(tag),
+            ItemFilter::Field(name, comparison, expected) => item
+                .annotations
+                .get_field(name)
+                .or_else(|| holder.get_field(name))
+                .is_some_and(|value| comparison.matches_value(value, expected)),
+        }
+    }
+}
+
+/// Filters on the record as a whole.
+pub enum HolderFilter {
+    Rating(Comparison, u8),
+    /// Number of plays of all copies, `plays:0` finds records never played.
+    Plays(Comparison, usize),
+}
+
+impl HolderFilter {
+    fn parse(token: &str) -> Option<HolderFilter> {
+        let (key, comparison, value) = split_filter(token)?;
+        match key.as_str() {
+            "rating" => Some(HolderFilter::Rating(comparison, value.parse().ok()?)),
+            "plays" => Some(HolderFilter::Plays(comparison, value.parse().ok()?)),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, holder: &ItemHolder) -> bool {
+        match self {
+            HolderFilter::Rating(comparison, rating) => holder
+                .rating
+                .is_some_and(|r| comparison.matches(r, *rating)),
+            HolderFilter::Plays(comparison, plays) => {
+                comparison.matches(holder.plays().count(), *plays)
+            }
+        }
+    }
+}
+
+/// Result order set with `sort:<key>`, results keep the database order otherwise.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortKey {
+    /// Most played this year first.
+    Plays,
+    /// Best rated first.
+    Rating,
+}
+
+/// Local search query: free text words matched against the whole holder
+/// plus `key:value`, `key>=value` and `key<=value` filters. Tokens that look like
+/// a filter but have an unknown key are searched for as words, e.g. `live:1977`.
+pub struct SearchQuery {
+    pub words: Vec<String>,
+    pub item_filters: Vec<ItemFilter>,
+    pub holder_filters: Vec<HolderFilter>,
+    pub sort: Option<SortKey>,
+}
+
+impl SearchQuery {
+    /// Parses `query`, the keys of `custom_fields` can be used as filters besides
+    /// the built-in ones.
+    pub fn parse(query: &str, custom_fields: &[CustomField]) -> Self {
+        let mut words = vec![];
+        let mut item_filters = vec![];
+        let mut holder_filters = vec![];
+        let mut sort = None;
+        for token in tokenize(query) {
+            match token.to_ascii_lowercase().as_str() {
+                "sort:plays" => sort = Some(SortKey::Plays),
+                "sort:rating" => sort = Some(SortKey::Rating),
+                _ => match HolderFilter::parse(&token) {
+                    Some(filter) => holder_filters.push(filter),
+                    None => match ItemFilter::parse(&token, custom_fields) {
+                        Some(filter) => item_filters.push(filter),
+                        None => words.push(token.to_ascii_lowercase()),
+                    },
+                },
+            }
+        }
+        SearchQuery {
+            words,
+            item_filters,
+            holder_filters,
+            sort,
+        }
+    }
+
+    /// Orders the results by the `sort:` key of the query, if any.
+    pub fn sort(&self, results: &mut [ItemHolder]) {
+        match self.sort {
+            Some(SortKey::Plays) => {
+                let year = Local::now().year();
+                results.sort_by_key(|h| Reverse(h.plays_in_year(year)))
+            }
+            Some(SortKey::Rating) => results.sort_by_key(|h| Reverse(h.rating)),
+            None => {}
+        }
+    }
+
+    /// Whether the holder contains every word and passes all filters.
+    pub fn matches(&self, holder: &ItemHolder) -> bool {
+        let json = serde_json::to_string(holder)
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        self.words.iter().all(|w| json.contains(w.as_str()))
+            && self.holder_filters.iter().all(|f| f.matches(holder))
+            && (self.item_filters.is_empty()
+                || holder.items.iter().any(|i| self.matches_item(holder, i)))
+    }
+
+    /// Whether a copy of the holder passes all item filters.
+    pub fn matches_item(&self, holder: &ItemHolder, item: &Item) -> bool {
+        self.item_filters
+            .iter()
+            .all(|f| f.matches(&holder.annotations, item))
+    }
+}
+
+/// Splits on whitespace, keeping "double quoted" parts together.
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+fn split_filter(token: &str) -> Option<(String, Comparison, &str)> {
+    for (operator, comparison) in [
+        (">=", Comparison::AtLeast),
+        ("<=", Comparison::AtMost),
+        (":", Comparison::Equal),
+    ] {
+        if let Some((key, value)) = token.split_once(operator) {
+            return Some((key.to_ascii_lowercase(), comparison, value));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        annotations::FieldKind,
+        item_holder::{ItemEvent, ItemEventType, Location},
+        record::Record,
+    };
+
+    #[test]
+    fn only_declared_fields_are_filters() {
+        let fields = vec![CustomField {
+            name: "Pressing".to_string(),
+            kind: FieldKind::Text,
+        }];
+        let query = SearchQuery::parse("pressing:first live:1977 tag:dj", &fields);
+        assert_eq!(query.words, vec!["live:1977"]);
+        assert_eq!(query.item_filters.len(), 2);
+        assert!(matches!(
+            &query.item_filters[0],
+            ItemFilter::Field(name, Comparison::Equal, value) if name == "Pressing" && value == "first"
+        ));
+    }
+
+    #[test]
+    fn most_played_this_year_comes_first() {
+        let holder = |id: i64, plays: usize| {
+            let mut holder = ItemHolder::new_with_item(Record {
+                id,
+                ..Record::default()
+            });
+            for _ in 0..plays {
+                holder.items[0]
+                    .events
+                    .push(ItemEvent::new(ItemEventType::Played));
+            }
+            holder
+        };
+        let mut results = vec![holder(1, 1), holder(2, 3), holder(3, 0)];
+        SearchQuery::parse("sort:plays", &[]).sort(&mut results);
+        let ids: Vec<_> = results.iter().map(|h| h.record.id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+
+        let mut results = vec![holder(1, 0), holder(2, 0), holder(3, 0)];
+        results[0].rating = Some(2);
+        results[2].rating = Some(5);
+        SearchQuery::parse("sort:rating", &[]).sort(&mut results);
+        let ids: Vec<_> = results.iter().map(|h| h.record.id).collect();
+        assert_eq!(ids, vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn grade_filters_compare_on_the_goldmine_scale() {
+        let mut holder = ItemHolder::new_with_item(Record::default());
+        holder.items[0].media_condition = Some(Grade::NearMint);
+        holder.items[0].sleeve_condition = Some(Grade::VeryGood);
+        let matches = |query: &str| SearchQuery::parse(query, &[]).matches(&holder);
+        assert!(matches("media:nm"));
+        assert!(matches("media>=VG+ sleeve<=vg"));
+        assert!(!matches("sleeve>=VG+"));
+        assert!(!matches("media<=G"));
+        // Unknown grades are searched as text.
+        assert!(!matches("media:great"));
+    }
+
+    #[test]
+    fn locations_match_the_place_and_everything_below() {
+        let mut holder = ItemHolder::new_with_item(Record::default());
+        holder.items[0].location = Some(Location::parse("Living Room/A/3/12").unwrap());
+        let matches = |query: &str| SearchQuery::parse(query, &[]).matches(&holder);
+        assert!(matches("loc:\"living room\""));
+        assert!(matches("location:\"Living Room/A/\""));
+        assert!(matches("loc:\"Living Room/A/3/12\""));
+        assert!(!matches("loc:\"Living Room/A/31\""));
+        assert!(!matches("loc:Living"));
+    }
+
+    #[test]
+    fn quoted_parts_stay_together() {
+        assert_eq!(
+            tokenize("  loc:\"Living Room/A\"   \"led zeppelin\" iv"),
+            vec!["loc:Living Room/A", "led zeppelin", "iv"]
+        );
+    }
+
+    #[test]
+    fn tags_and_fields_of_the_record_count_for_its_copies() {
+        let fields = vec![
+            CustomField {
+                name: "Weight".to_string(),
+                kind: FieldKind::Number,
+            },
+            CustomField {
+                name: "Pressing".to_string(),
+                kind: FieldKind::Text,
+            },
+        ];
+        let mut holder = ItemHolder::new_with_item(Record::default());
+        holder.annotations.set_tags("Jazz, DJ");
+        holder
+            .annotations
+            .set_field(&fields, "pressing = First")
+            .unwrap();
+        holder.items[0].annotations.set_tags("signed");
+        holder.items[0]
+            .annotations
+            .set_field(&fields, "weight = 180")
+            .unwrap();
+        let matches = |query: &str| SearchQuery::parse(query, &fields).matches(&holder);
+        assert!(matches("tag:dj tag:SIGNED"));
+        assert!(!matches("tag:rock"));
+        assert!(matches("pressing:first weight>=140"));
+        // Numbers compare by value, not as text.
+        assert!(matches("weight>=90 weight<=180"));
+        assert!(!matches("weight>=200"));
+        assert!(!matches("pressing:reissue"));
+    }
+}
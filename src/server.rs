@@ -0,0 +1,323 @@
+use std::{
+    io::Cursor,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use record_catalog::{
+    borrowers::Borrowers,
+    database::Database,
+    models::{
+        annotations::CustomField,
+        error::{AppError, Result},
+        item_holder::{ItemEventType, Loan},
+        settings::Settings,
+    },
+    search::SearchQuery,
+};
+
+type JsonResponse = Response<Cursor<Vec<u8>>>;
+
+#[derive(Deserialize)]
+struct EventRequest {
+    /// Event label as shown in the app, e.g. "Cleaned" or "Note".
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LendRequest {
+    borrower: String,
+    #[serde(default)]
+    due: Option<NaiveDate>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Serves the collection as JSON until the process is interrupted. Requests are handled
+/// one at a time and mutations go through the same `Database` and `Item` methods as the app.
+pub fn serve(settings: &Settings, database: Database, address: &str) -> Result<()> {
+    let token = settings
+        .api_token
+        .clone()
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| {
+            AppError::Configuration(
+                "Set api_token in settings.json before serving the API".to_string(),
+            )
+        })?;
+    let server = Server::http(address)
+        .map_err(|e| AppError::Network(format!("Can't serve on {}: {}", address, e)))?;
+    eprintln!("Serving the API on http://{}", address);
+
+    let mut api = Api {
+        database,
+        borrowers: Borrowers::new(&settings.borrowers_path)?,
+        custom_fields: settings.custom_fields.clone(),
+        token,
+    };
+    // Stops on Ctrl-C so the database is dropped and its lock released.
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        signal_hook::flag::register(signal, stop.clone())
+            .map_err(|e| AppError::Terminal(format!("Can't handle Ctrl-C: {}", e)))?;
+    }
+    while !stop.load(Ordering::Relaxed) {
+        let request = server
+            .recv_timeout(Duration::from_millis(200))
+            .map_err(|e| AppError::Network(format!("Receiving a request failed: {}", e)))?;
+        if let Some(mut request) = request {
+            let response = api.handle(&mut request);
+            if let Err(e) = request.respond(response) {
+                eprintln!("Failed to send response: {}", e);
+            }
+        }
+    }
+    eprintln!("API stopped");
+    Ok(())
+}
+
+struct Api {
+    database: Database,
+    borrowers: Borrowers,
+    custom_fields: Vec<CustomField>,
+    token: String,
+}
+
+impl Api {
+    fn handle(&mut self, request: &mut Request) -> JsonResponse {
+        if !self.is_authorized(request) {
+            return error_response(401, "Missing or invalid bearer token");
+        }
+        match self.route(request) {
+            Ok(body) => {
+                let etag = format!(
+                    "\"{}\"",
+                    Sha256::digest(body.as_bytes())[..16]
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<String>()
+                );
+                let cached = header_value(request, "If-None-Match")
+                    .is_some_and(|tags| tags.split(',').any(|t| t.trim() == etag));
+                let response = match cached && *request.method() == Method::Get {
+                    true => Response::from_data(vec![]).with_status_code(304),
+                    false => json_response(200, body),
+                };
+                with_header(
+                    with_header(response, "ETag", &etag),
+                    "Cache-Control",
+                    "no-cache",
+                )
+            }
+            Err(e) => {
+                let status = match e {
+                    AppError::NotFound(_) => 404,
+                    AppError::ExternalChange(_) => 409,
+                    AppError::Storage(_)
+                    | AppError::Network(_)
+                    | AppError::Configuration(_)
+                    | AppError::Terminal(_) => 500,
+                    AppError::Parse(_) | AppError::Validation(_) => 400,
+                };
+                error_response(status, &e.to_string())
+            }
+        }
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        let expected = format!("Bearer {}", self.token);
+        header_value(request, "Authorization").is_some_and(|value| {
+            // Compares every byte so the time taken doesn't reveal how much of the token matched.
+            value.len() == expected.len()
+                && value
+                    .bytes()
+                    .zip(expected.bytes())
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+        })
+    }
+
+    /// Returns the JSON body of a successful response.
+    fn route(&mut self, request: &mut Request) -> Result<String> {
+        if self.database.has_external_changes()? {
+            self.database.reload()?;
+        }
+        // The app and the command line register borrowers too, the list is small.
+        self.borrowers.reload()?;
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let id = |index: usize| -> Result<i64> {
+            segments[index]
+                .parse()
+                .map_err(|_| AppError::NotFound(format!("Invalid id \"{}\"", segments[index])))
+        };
+
+        match (request.method(), segments.as_slice()) {
+            (Method::Get, ["records"]) => {
+                let param = |name: &str| query_param(query, name);
+                let query =
+                    SearchQuery::parse(&param("q").unwrap_or_default(), &self.custom_fields);
+                let results = self.database.search(&query, None);
+                let offset = param("offset").and_then(|o| o.parse().ok()).unwrap_or(0);
+                let limit = param("limit")
+                    .and_then(|l| l.parse().ok())
+                    .unwrap_or(usize::MAX);
+                let page: Vec<_> = results.iter().skip(offset).take(limit).collect();
+                Ok(serde_json::to_string(&page)?)
+            }
+            (Method::Get, ["records", _]) => {
+                Ok(serde_json::to_string(self.database.holder(id(1)?)?)?)
+            }
+            (Method::Post, ["records", _, "items", _, "events"]) => {
+                let body: EventRequest = read_json(request)?;
+                let event_type = ItemEventType::parse(&body.event_type).ok_or_else(|| {
+                    AppError::Validation(format!("Unknown event type \"{}\"", body.event_type))
+                })?;
+                let message = body.message.filter(|m| !m.trim().is_empty());
+                let item = self
+                    .database
+                    .modify_item(id(1)?, id(3)?, |item| item.add_event(event_type, message))?;
+                Ok(serde_json::to_string(&item)?)
+            }
+            (Method::Post, ["records", _, "items", _, "lend"]) => {
+                let body: LendRequest = read_json(request)?;
+                let due = body.due.unwrap_or_else(Loan::default_due);
+                let (record_id, item_id) = (id(1)?, id(3)?);
+                let database = &mut self.database;
+                let item = self.borrowers.registering(|borrowers| {
+                    database.modify_item(record_id, item_id, |item| {
+                        item.lend(borrowers, &body.borrower, due, body.message)
+                    })
+                })?;
+                Ok(serde_json::to_string(&item)?)
+            }
+            (Method::Post, ["records", _, "items", _, "return"]) => {
+                let borrowers = &self.borrowers;
+                let item = self
+                    .database
+                    .modify_item(id(1)?, id(3)?, |item| item.return_loan(borrowers))?;
+                Ok(serde_json::to_string(&item)?)
+            }
+            (method, _) => Err(AppError::NotFound(format!(
+                "No route for {} {}",
+                method, path
+            )))?,
+        }
+    }
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(request: &mut Request) -> Result<T> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|e| AppError::Network(format!("Couldn't read the request body: {}", e)))?;
+    serde_json::from_str(&body).map_err(|e| AppError::Parse(format!("Invalid request body: {}", e)))
+}
+
+fn header_value(request: &Request, name: &'static str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.to_string())
+}
+
+/// Adds a header, values that aren't valid in a header are left out.
+fn with_header(response: JsonResponse, name: &str, value: &str) -> JsonResponse {
+    match Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+        Ok(header) => response.with_header(header),
+        Err(_) => response,
+    }
+}
+
+fn json_response(status: u16, body: String) -> JsonResponse {
+    with_header(
+        Response::from_string(body).with_status_code(status),
+        "Content-Type",
+        "application/json",
+    )
+}
+
+fn error_response(status: u16, message: &str) -> JsonResponse {
+    let body = serde_json::to_string(&ErrorResponse {
+        error: message.to_string(),
+    })
+    .unwrap_or_default();
+    json_response(status, body)
+}
+
+/// The decoded value of a `name=value` pair of the query string.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|h| std::str::from_utf8(h).ok());
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_values_are_decoded() {
+        assert_eq!(percent_decode("rock+n%27+roll"), "rock n' roll");
+        assert_eq!(percent_decode("caf%C3%A9"), "café");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn query_params_are_found_by_name() {
+        let query = "q=tag%3Adj&sort=rating&limit=";
+        assert_eq!(query_param(query, "q").as_deref(), Some("tag:dj"));
+        assert_eq!(query_param(query, "limit").as_deref(), Some(""));
+        assert_eq!(query_param(query, "so"), None);
+        assert_eq!(query_param("", "q"), None);
+    }
+}
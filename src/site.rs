@@ -0,0 +1,428 @@
+//! Static web catalog of the collection.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::Path,
+};
+
+use crate::{
+    covers::CoverStore,
+    export::html_escape,
+    models::{
+        error::{AppError, Result},
+        item_holder::{grade_code, ItemHolder, ItemState},
+        record::{Record, Track},
+    },
+    search::SearchQuery,
+};
+
+/// The names a record is listed under on an index page.
+type IndexKeys = fn(&Record) -> Vec<String>;
+
+/// Lists the files of the last run, relative to the site directory.
+const MANIFEST: &str = ".generated-files";
+
+/// Writes a read-only catalog of the given records to `dir`: one page per record
+/// under `records/`, the index and pages listing the records by artist, label and
+/// genre. Only public release data and the grades of available copies are shown,
+/// nothing about purchases, locations or borrowers. Pages and covers an earlier run
+/// wrote for records that are no longer included are removed, other files are kept.
+pub fn generate_site(
+    holders: &[ItemHolder],
+    query: &SearchQuery,
+    covers: &CoverStore,
+    dir: &str,
+    title: &str,
+) -> Result<usize> {
+    let dir = Path::new(dir);
+    for sub_dir in ["records", "covers"] {
+        let path = dir.join(sub_dir);
+        fs::create_dir_all(&path).map_err(|e| AppError::file(&path, e))?;
+    }
+    let mut files = SiteFiles {
+        dir,
+        written: BTreeSet::new(),
+    };
+
+    let holders: Vec<&ItemHolder> = holders
+        .iter()
+        .filter(|h| {
+            h.items
+                .iter()
+                .any(|i| i.is_in_collection() && query.matches_item(h, i))
+        })
+        .collect();
+
+    for holder in &holders {
+        let cover = copy_cover(covers, &holder.record, &mut files)?;
+        let body = record_page(holder, query, cover.as_deref());
+        files.write(
+            &format!("records/{}.html", holder.record.id),
+            page(&holder.record.title, title, "../", &body),
+        )?;
+    }
+
+    let records: Vec<&Record> = holders.iter().map(|h| &h.record).collect();
+    let all = record_list(&records);
+    files.write("index.html", page(title, title, "", &all))?;
+
+    let indexes: [(&str, &str, IndexKeys); 3] = [
+        ("artists.html", "Artists", |r| {
+            r.artists.iter().map(|a| a.name.clone()).collect()
+        }),
+        ("labels.html", "Labels", |r| {
+            r.labels.iter().map(|l| l.name.clone()).collect()
+        }),
+        ("genres.html", "Genres", |r| r.genres.clone()),
+    ];
+    for (file, heading, keys) in indexes {
+        let mut groups: BTreeMap<String, Vec<&Record>> = BTreeMap::new();
+        for record in &records {
+            for key in keys(record) {
+                groups.entry(key).or_default().push(record);
+            }
+        }
+        let slugs = unique_slugs(groups.keys());
+        let mut body = "<ul class=\"toc\">".to_string();
+        for (name, slug) in groups.keys().zip(&slugs) {
+            body += &format!("<li><a href=\"#{}\">{}</a></li>", slug, html_escape(name));
+        }
+        body += "</ul>\n";
+        for ((name, records), slug) in groups.iter().zip(&slugs) {
+            body += &format!(
+                "<h2 id=\"{}\">{}</h2>\n{}",
+                slug,
+                html_escape(name),
+                record_list(records)
+            );
+        }
+        files.write(file, page(heading, title, "", &body))?;
+    }
+    files.finish()?;
+    Ok(holders.len())
+}
+
+/// The files written to the site directory by one run.
+struct SiteFiles<'a> {
+    dir: &'a Path,
+    written: BTreeSet<String>,
+}
+
+impl SiteFiles<'_> {
+    /// Writes `name`, a path relative to the site directory.
+    fn write(&mut self, name: &str, contents: impl AsRef<[u8]>) -> Result<()> {
+        let path = self.dir.join(name);
+        fs::write(&path, contents).map_err(|e| AppError::file(&path, e))?;
+        self.written.insert(name.to_string());
+        Ok(())
+    }
+
+    /// Removes what the last run wrote but this one didn't and lists this run's files
+    /// for the next one. Names leaving the site directory are ignored.
+    fn finish(self) -> Result<()> {
+        let manifest = self.dir.join(MANIFEST);
+        let previous = match fs::read_to_string(&manifest) {
+            Ok(previous) => previous,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => Err(AppError::file(&manifest, e))?,
+        };
+        for name in previous.lines() {
+            if self.written.contains(name)
+                || name.starts_with('/')
+                || name.split(['/', '\\']).any(|part| part == "..")
+            {
+                continue;
+            }
+            let path = self.dir.join(name);
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(AppError::file(&path, e))?,
+                _ => {}
+            }
+        }
+        let list: String = self
+            .written
+            .iter()
+            .map(|name| name.clone() + "\n")
+            .collect();
+        fs::write(&manifest, list).map_err(|e| AppError::file(&manifest, e))
+    }
+}
+
+/// Copies the downloaded cover into the site, returns its path relative to the site root.
+fn copy_cover(
+    covers: &CoverStore,
+    record: &Record,
+    files: &mut SiteFiles,
+) -> Result<Option<String>> {
+    let source = match covers.path(record.id) {
+        Some(source) => source,
+        None => return Ok(None),
+    };
+    let bytes = fs::read(&source).map_err(|e| AppError::file(&source, e))?;
+    let extension = image::guess_format(&bytes)
+        .ok()
+        .and_then(|f| f.extensions_str().first().copied())
+        .unwrap_or("jpg");
+    let name = format!("covers/{}.{}", record.id, extension);
+    files.write(&name, bytes)?;
+    Ok(Some(name))
+}
+
+fn record_list(records: &[&Record]) -> String {
+    let mut list = "<ul class=\"records\">\n".to_string();
+    for record in records {
+        list += &format!(
+            "<li><a href=\"records/{}.html\">{} &ndash; {}</a>{}</li>\n",
+            record.id,
+            html_escape(&artists(record)),
+            html_escape(&record.title),
+            match record.year {
+                0 => "".to_string(),
+                year => format!(" <span class=\"year\">{}</span>", year),
+            }
+        );
+    }
+    list + "</ul>\n"
+}
+
+fn record_page(holder: &ItemHolder, query: &SearchQuery, cover: Option<&str>) -> String {
+    let record = &holder.record;
+    let mut body = format!(
+        "<p class=\"artist\">{}</p>\n",
+        html_escape(&artists(record))
+    );
+    if let Some(cover) = cover {
+        body += &format!(
+            "<img class=\"cover\" src=\"../{}\" alt=\"Cover of {}\">\n",
+            cover,
+            html_escape(&record.title)
+        );
+    }
+
+    let labels: Vec<String> = record
+        .labels
+        .iter()
+        .map(|l| format!("{} &ndash; {}", html_escape(&l.name), html_escape(&l.catno)))
+        .collect();
+    let formats: Vec<String> = record
+        .formats
+        .iter()
+        .map(|f| html_escape(&f.get_line()))
+        .collect();
+    let details = [
+        ("Label", labels.join("<br>")),
+        ("Format", formats.join("<br>")),
+        (
+            "Released",
+            html_escape(record.released.as_deref().unwrap_or_default()),
+        ),
+        (
+            "Year",
+            match record.year {
+                0 => "".to_string(),
+                year => year.to_string(),
+            },
+        ),
+        (
+            "Country",
+            html_escape(record.country.as_deref().unwrap_or_default()),
+        ),
+        ("Genre", html_escape(&record.genres.join(", "))),
+        ("Style", html_escape(&record.styles.join(", "))),
+    ];
+    body += "<table class=\"details\">\n";
+    for (name, value) in details.iter().filter(|(_, v)| !v.is_empty()) {
+        body += &format!("<tr><th>{}</th><td>{}</td></tr>\n", name, value);
+    }
+    body += "</table>\n";
+
+    let available: Vec<_> = holder
+        .items
+        .iter()
+        .filter(|i| i.state() == ItemState::InCollection && query.matches_item(holder, i))
+        .collect();
+    body += "<h2>Availability</h2>\n";
+    body += &match available.len() {
+        0 => "<p>Currently not available.</p>\n".to_string(),
+        count => {
+            let copies: String = available
+                .iter()
+                .map(|i| match (i.media_condition, i.sleeve_condition) {
+                    (None, None) => "<li>Ungraded</li>".to_string(),
+                    (media, sleeve) => format!(
+                        "<li>Media {}, sleeve {}</li>",
+                        grade_code(media),
+                        grade_code(sleeve)
+                    ),
+                })
+                .collect();
+            format!(
+                "<p>{} {} available.</p>\n<ul>{}</ul>\n",
+                count,
+                if count == 1 { "copy" } else { "copies" },
+                copies
+            )
+        }
+    };
+
+    if !record.tracklist.is_empty() {
+        body += "<h2>Tracklist</h2>\n<table class=\"tracklist\">\n";
+        for track in &record.tracklist {
+            body += &track_rows(track);
+        }
+        body += "</table>\n";
+    }
+    if let Some(notes) = record.notes.as_ref().filter(|n| !n.is_empty()) {
+        body += &format!(
+            "<h2>Notes</h2>\n<p class=\"notes\">{}</p>\n",
+            html_escape(notes)
+        );
+    }
+    body
+}
+
+fn track_rows(track: &Track) -> String {
+    let mut rows = match track.type_.as_str() {
+        "heading" => format!(
+            "<tr class=\"heading\"><td colspan=\"3\">{}</td></tr>\n",
+            html_escape(&track.title)
+        ),
+        _ => format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            html_escape(&track.position),
+            html_escape(&track.title),
+            html_escape(&track.duration)
+        ),
+    };
+    for sub_track in track.sub_tracks.iter().flatten() {
+        rows += &track_rows(sub_track);
+    }
+    rows
+}
+
+fn artists(record: &Record) -> String {
+    record
+        .artists
+        .iter()
+        .map(|a| a.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Anchor name for an index entry.
+/// Anchors for the names, numbered when different names give the same slug,
+/// e.g. "A&B" and "A B".
+fn unique_slugs<'a>(names: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut used = BTreeSet::new();
+    names
+        .map(|name| {
+            let base = slug(name);
+            let mut slug = base.clone();
+            let mut number = 1;
+            while !used.insert(slug.clone()) {
+                number += 1;
+                slug = format!("{}-{}", base, number);
+            }
+            slug
+        })
+        .collect()
+}
+
+fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '-',
+        })
+        .collect()
+}
+
+fn page(heading: &str, site_title: &str, root: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{heading} &ndash; {site}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 50em; margin: 2em auto; padding: 0 1em; color: #222; }}
+nav a {{ margin-right: 1em; }}
+a {{ color: #2a5db0; text-decoration: none; }}
+.year {{ color: #888; }}
+.cover {{ float: right; max-width: 40%; margin: 0 0 1em 1em; }}
+.details th, .tracklist td:first-child {{ text-align: left; color: #666; padding-right: 1em; vertical-align: top; }}
+.tracklist td:last-child {{ color: #888; padding-left: 1em; }}
+.tracklist .heading td {{ font-weight: bold; padding-top: 0.5em; }}
+.toc li {{ display: inline; margin-right: 1em; }}
+.notes {{ white-space: pre-line; }}
+</style>
+</head>
+<body>
+<nav><a href="{root}index.html">All records</a><a href="{root}artists.html">Artists</a><a href="{root}labels.html">Labels</a><a href="{root}genres.html">Genres</a></nav>
+<h1>{heading}</h1>
+{body}</body>
+</html>
+"#,
+        heading = html_escape(heading),
+        site = html_escape(site_title),
+        root = root,
+        body = body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_with_the_same_slug_get_their_own_anchor() {
+        let names = ["A B", "A&B", "a b", "C"].map(String::from);
+        assert_eq!(
+            unique_slugs(names.iter()),
+            vec!["a-b", "a-b-2", "a-b-3", "c"]
+        );
+    }
+
+    #[test]
+    fn pages_of_records_left_out_are_removed() {
+        let dir = std::env::temp_dir().join(format!("site-test-{}", std::process::id()));
+        let covers = CoverStore::new(dir.join("no-covers").to_str().unwrap()).unwrap();
+        let holder = |id: i64, tag: &str| {
+            let mut holder = ItemHolder::new_with_item(Record {
+                id,
+                title: format!("Record {}", id),
+                ..Record::default()
+            });
+            holder.annotations.tags.push(tag.to_string());
+            holder
+        };
+        let holders = [holder(1, "keep"), holder(2, "sold")];
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("robots.txt"), "").unwrap();
+        fs::write(dir.join(MANIFEST), "../outside.txt\n").unwrap();
+
+        let all = SearchQuery::parse("", &[]);
+        let path = dir.to_str().unwrap();
+        assert_eq!(
+            generate_site(&holders, &all, &covers, path, "Site").unwrap(),
+            2
+        );
+        assert!(dir.join("records/2.html").exists());
+
+        let kept = SearchQuery::parse("tag:keep", &[]);
+        assert_eq!(
+            generate_site(&holders, &kept, &covers, path, "Site").unwrap(),
+            1
+        );
+        assert!(dir.join("records/1.html").exists());
+        assert!(!dir.join("records/2.html").exists());
+        assert!(dir.join("robots.txt").exists());
+        let manifest = fs::read_to_string(dir.join(MANIFEST)).unwrap();
+        assert!(manifest.lines().any(|l| l == "records/1.html"));
+        assert!(!manifest.contains("2.html"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,774 @@
+use std::{
+    collections::BTreeMap,
+    mem,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::Instant,
+};
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+use tui_textarea::TextArea;
+
+use record_catalog::{
+    borrowers::Borrowers,
+    covers::{CoverStore, GraphicsProtocol},
+    database::Database,
+    discogs::DiscogsClient,
+    labels::parse_label_code,
+    models::{
+        annotations::{Annotations, CustomField},
+        error::{AppError, Result},
+        item_holder::{
+            Acquisition, Grade, Item, ItemEvent, ItemEventType, ItemHolder, LoanTerms, Location,
+        },
+        query::DiscogsSearchResultRelease,
+        record::Record,
+        settings::Settings,
+    },
+    search::SearchQuery,
+    stats::{collection_stats, CollectionStats},
+    valuation::{collection_value, CollectionValue, PriceCache},
+};
+
+use crate::gui::cover::CoverView;
+
+use super::{
+    editor::Editor,
+    list::StatefulList,
+    stateful::{ItemInput, StatefulItem, StatefulItemHolder},
+};
+
+use strum::{EnumIter, IntoEnumIterator};
+
+#[derive(EnumIter, Clone, PartialEq)]
+pub enum AppPages {
+    Home,
+    Search,
+    WebSearch,
+    Editor,
+    Browse,
+    OnLoan,
+    Analytics,
+}
+
+pub trait AppPage {
+    fn get_title(&self) -> &str;
+    fn show_input(&self) -> bool;
+    fn get_number(&self) -> usize;
+}
+
+impl AppPage for AppPages {
+    fn get_title(&self) -> &str {
+        match self {
+            AppPages::Home => "Home",
+            AppPages::WebSearch => "Web search",
+            AppPages::Search => "Search",
+            AppPages::Editor => "Editor",
+            AppPages::Browse => "Browse",
+            AppPages::OnLoan => "On loan",
+            AppPages::Analytics => "Analytics",
+        }
+    }
+
+    fn get_number(&self) -> usize {
+        match self {
+            AppPages::Home => 0,
+            AppPages::Search => 1,
+            AppPages::WebSearch => 2,
+            AppPages::Editor => 3,
+            AppPages::Browse => 4,
+            AppPages::OnLoan => 5,
+            AppPages::Analytics => 6,
+        }
+    }
+
+    fn show_input(&self) -> bool {
+        match self {
+            AppPages::Home => false,
+            AppPages::WebSearch => true,
+            AppPages::Search => true,
+            AppPages::Editor => false,
+            AppPages::Browse => false,
+            AppPages::OnLoan => true,
+            AppPages::Analytics => false,
+        }
+    }
+}
+
+pub struct App<'a> {
+    pub active: AppPages,
+    pub pages: Vec<AppPages>,
+    pub is_main_input: bool,
+    pub main_input: TextArea<'a>,
+    pub is_side_input: bool,
+    pub side_input: TextArea<'a>,
+    /// Database of the active collection, the others stay in `collections`.
+    pub database: Database,
+    pub collections: Vec<Collection>,
+    pub active_collection: usize,
+    pub discogs_client: DiscogsClient,
+    pub message_box: String,
+    pub query_results: StatefulList<DiscogsSearchResultRelease>,
+    pub search: Search,
+    pub editor: Editor<'a>,
+    pub link_record: Option<Record>,
+    pub watch_database: bool,
+    pub last_watch: Instant,
+    pub external_change: bool,
+    pub price_cache_path: String,
+    pub valuation: Option<CollectionValue>,
+    /// Result of the valuation running in the background.
+    pub valuing: Option<Receiver<Result<CollectionValue>>>,
+    pub locations: StatefulList<LocationGroup>,
+    pub borrowers: Borrowers,
+    pub loans: StatefulList<LoanEntry>,
+    pub custom_fields: Vec<CustomField>,
+    pub cover: CoverView,
+    pub stats: CollectionStats,
+    /// Which breakdown the Analytics page shows, an index into `STATS_VIEWS`.
+    pub stats_view: usize,
+}
+
+pub const STATS_VIEWS: [&str; 7] = [
+    "Genres", "Styles", "Formats", "Labels", "Artists", "Decades", "Growth",
+];
+
+impl App<'_> {
+    pub fn new(settings: Settings) -> Result<Self> {
+        let discogs_client: DiscogsClient =
+            DiscogsClient::new(&settings.discogs_key, settings.currency.clone());
+
+        let mut collections = settings
+            .collections()
+            .into_iter()
+            .map(|c| {
+                Ok(Collection {
+                    database: Some(Database::new(&c.database_path)?),
+                    name: c.name,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let database = collections
+            .first_mut()
+            .and_then(|c| c.database.take())
+            .ok_or_else(|| {
+                AppError::Configuration("No collection configured in settings.json".to_string())
+            })?;
+
+        Ok(App {
+            pages: AppPages::iter().collect::<Vec<_>>(),
+            active: AppPages::Home,
+            database,
+            collections,
+            active_collection: 0,
+            discogs_client,
+            is_main_input: false,
+            main_input: TextArea::default(),
+            is_side_input: false,
+            side_input: TextArea::default(),
+            query_results: StatefulList::with_items(vec![]),
+            message_box: "".to_string(),
+            search: Search::empty(),
+            editor: Editor::empty(),
+            link_record: None,
+            watch_database: settings.watch_database,
+            last_watch: Instant::now(),
+            external_change: false,
+            price_cache_path: settings.price_cache_path.clone(),
+            valuation: None,
+            valuing: None,
+            locations: StatefulList::with_items(vec![]),
+            borrowers: Borrowers::new(&settings.borrowers_path)?,
+            loans: StatefulList::with_items(vec![]),
+            custom_fields: settings.custom_fields,
+            cover: CoverView::new(
+                CoverStore::new(&settings.covers_path)?,
+                settings
+                    .cover_protocol
+                    .unwrap_or_else(GraphicsProtocol::detect),
+            ),
+            stats: CollectionStats::default(),
+            stats_view: 0,
+        })
+    }
+
+    pub fn web_search(&mut self) -> Result<()> {
+        let query = &self.main_input.lines()[0];
+        let results = self.discogs_client.query(query)?.get_releases();
+        self.message_box = format!("Found {} results", results.len());
+        self.query_results = StatefulList::with_items(results);
+        Ok(())
+    }
+
+    pub fn search(&mut self, item_holder: Option<Record>) -> Result<()> {
+        let query = &self.main_input.lines()[0];
+        if item_holder.is_none() {
+            if let Some((record_id, item_id)) = parse_label_code(query) {
+                return self.select_scanned_item(record_id, item_id);
+            }
+        }
+        let query = SearchQuery::parse(query, &self.custom_fields);
+        let results = self.database.search(&query, item_holder);
+        self.message_box = format!("Found {} results", results.len());
+        self.search.list =
+            StatefulList::with_items(results.into_iter().map(StatefulItemHolder::from).collect());
+        self.search.list.next();
+        Ok(())
+    }
+
+    /// Shows the record of a scanned label with its copy selected.
+    pub fn select_scanned_item(&mut self, record_id: i64, item_id: i64) -> Result<()> {
+        let holder = self.database.holder(record_id)?;
+        let index = holder
+            .items
+            .iter()
+            .position(|i| i.id == item_id)
+            .ok_or_else(|| {
+                AppError::NotFound(format!("No copy #{} of {}", item_id, holder.record.title))
+            })?;
+        self.message_box = format!("Copy #{} of {}", item_id, holder.record.title);
+        let mut holder = StatefulItemHolder::from(holder.clone());
+        holder.list.state.select(Some(index));
+        self.search.list = StatefulList::with_items(vec![holder]);
+        self.search.list.next();
+        Ok(())
+    }
+
+    pub fn next_collection(&mut self) -> Result<()> {
+        let index = (self.active_collection + 1) % self.collections.len();
+        self.switch_collection(index)
+    }
+
+    pub fn switch_collection(&mut self, index: usize) -> Result<()> {
+        if index == self.active_collection {
+            return Ok(());
+        }
+        let database = self.collections[index]
+            .database
+            .take()
+            .ok_or_else(|| AppError::Storage("Collection not loaded".to_string()))?;
+        self.collections[self.active_collection].database =
+            Some(mem::replace(&mut self.database, database));
+        self.active_collection = index;
+        self.search = Search::empty();
+        self.load_page(&self.active.clone());
+        self.message_box = format!("Switched to {}", self.collections[index].name);
+        Ok(())
+    }
+
+    pub fn collection_database(&self, index: usize) -> Option<&Database> {
+        match index == self.active_collection {
+            true => Some(&self.database),
+            false => self.collections.get(index)?.database.as_ref(),
+        }
+    }
+
+    /// Moves the selected copy into another collection, recording the move on both sides.
+    pub fn transfer_selected_item(&mut self, destination: &str) -> Result<()> {
+        let index = self
+            .collections
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(destination.trim()))
+            .ok_or_else(|| {
+                AppError::NotFound(format!("No collection named \"{}\"", destination.trim()))
+            })?;
+        if index == self.active_collection {
+            Err(AppError::Validation(
+                "Item is already in this collection".to_string(),
+            ))?
+        }
+        let source_name = self.collections[self.active_collection].name.clone();
+        let destination = &mut self.collections[index];
+        let destination_database = destination
+            .database
+            .as_mut()
+            .ok_or_else(|| AppError::Storage("Collection not loaded".to_string()))?;
+
+        let holder = self
+            .search
+            .list
+            .selected_mut()
+            .ok_or_else(|| AppError::NotFound("Record not selected".to_string()))?;
+        let stateful_item = holder
+            .list
+            .selected_mut()
+            .ok_or_else(|| AppError::NotFound("Item not selected".to_string()))?;
+        stateful_item.item = self.database.transfer_item(
+            holder.record.id,
+            stateful_item.item.id,
+            destination_database,
+            &source_name,
+            &destination.name,
+        )?;
+        Ok(())
+    }
+
+    /// Applies "<media> <sleeve>" grades to the selected copy, "-" keeps a grade as it is.
+    pub fn grade_selected_item(&mut self, grades: &str) -> Result<()> {
+        let parse = |code: Option<&str>| match code {
+            None | Some("-") => Ok(None),
+            Some(code) => Grade::parse(code)
+                .map(Some)
+                .ok_or_else(|| AppError::Validation(format!("Unknown grade \"{}\"", code))),
+        };
+        let mut codes = grades.split_whitespace();
+        let media = parse(codes.next())?;
+        let sleeve = parse(codes.next())?;
+
+        self.search
+            .update_selected_item(&mut self.database, |item| {
+                item.grade(media, sleeve);
+                Ok(())
+            })
+    }
+
+    /// Values the collection on a background thread, prices are fetched slowly to stay
+    /// within the Discogs rate limit. `poll_valuation` picks up the result.
+    pub fn value_collection(&mut self) -> Result<()> {
+        if self.valuing.is_some() {
+            Err(AppError::Validation(
+                "The collection is already being valued".to_string(),
+            ))?
+        }
+        let mut cache = PriceCache::new(&self.price_cache_path)?;
+        let data = self.database.holders().to_vec();
+        let client = self.discogs_client.clone();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = tx.send(collection_value(&data, &client, &mut cache));
+        });
+        self.valuing = Some(rx);
+        self.message_box = "Valuing the collection, this can take a while".to_string();
+        Ok(())
+    }
+
+    pub fn poll_valuation(&mut self) {
+        let result = match self.valuing.as_ref().map(|r| r.try_recv()) {
+            Some(Ok(result)) => result,
+            Some(Err(TryRecvError::Disconnected)) => Err(AppError::Network(
+                "Valuation stopped unexpectedly".to_string(),
+            )),
+            _ => return,
+        };
+        self.valuing = None;
+        self.message_box = match result {
+            Ok(value) => {
+                let mut message = format!(
+                    "Valued {} copies, {} without a price",
+                    value.priced, value.unpriced
+                );
+                if let Some(error) = &value.error {
+                    message += &format!(", {} failed: {}", value.failed, error);
+                }
+                self.valuation = Some(value);
+                message
+            }
+            Err(e) => format!("Couldn't value the collection: {}", e),
+        };
+    }
+
+    pub fn set_selected_item_acquisition(&mut self, text: &str) -> Result<()> {
+        let acquisition = Acquisition::parse(text)?;
+        self.search
+            .update_selected_item(&mut self.database, |item| {
+                item.acquisition = Some(acquisition);
+                Ok(())
+            })
+    }
+
+    pub fn move_selected_item(&mut self, text: &str) -> Result<()> {
+        let location = Location::parse(text)?;
+        self.search
+            .update_selected_item(&mut self.database, |item| {
+                item.move_to(location);
+                Ok(())
+            })
+    }
+
+    /// Adds a plain event to the selected copy, refusing copies that left the collection.
+    pub fn add_selected_item_event(&mut self, event_type: ItemEventType, text: &str) -> Result<()> {
+        let message = Some(text.trim().to_string()).filter(|m| !m.is_empty());
+        self.search
+            .update_selected_item(&mut self.database, |item| {
+                item.add_event(event_type, message)
+            })
+    }
+
+    /// Lends the selected copy, `text` is "borrower; YYYY-MM-DD; message" where
+    /// the due date defaults to four weeks from now and the message is optional.
+    pub fn lend_selected_item(&mut self, text: &str) -> Result<()> {
+        let terms = LoanTerms::parse(text)?;
+        let (search, database) = (&mut self.search, &mut self.database);
+        self.borrowers.registering(|borrowers| {
+            search.update_selected_item(database, |item| {
+                item.lend(borrowers, &terms.borrower, terms.due, terms.message)
+            })
+        })
+    }
+
+    pub fn return_selected_item(&mut self) -> Result<()> {
+        let borrowers = &self.borrowers;
+        self.search
+            .update_selected_item(&mut self.database, |item| item.return_loan(borrowers))
+    }
+
+    /// Applies "YYYY-MM-DD HH:MM; type; message" to the event selected in the timeline.
+    pub fn edit_selected_event(&mut self, text: &str) -> Result<()> {
+        let stateful_item = self
+            .search
+            .get_selected_item_mut()
+            .ok_or_else(|| AppError::NotFound("Item not selected".to_string()))?;
+        let index = stateful_item
+            .selected_event
+            .ok_or_else(|| AppError::NotFound("Event not selected".to_string()))?;
+        let event = stateful_item
+            .item
+            .events
+            .get(index)
+            .ok_or_else(|| AppError::NotFound("Event not found".to_string()))?
+            .edited(text)?;
+
+        let mut new_index = index;
+        self.search
+            .update_selected_item(&mut self.database, |item| {
+                new_index = item.edit_event(index, event)?;
+                Ok(())
+            })?;
+        if let Some(stateful_item) = self.search.get_selected_item_mut() {
+            stateful_item.selected_event = Some(new_index);
+        }
+        Ok(())
+    }
+
+    pub fn delete_selected_event(&mut self) -> Result<()> {
+        let index = self
+            .search
+            .get_selected_item_mut()
+            .and_then(|i| i.selected_event)
+            .ok_or_else(|| AppError::NotFound("Event not selected".to_string()))?;
+        self.search
+            .update_selected_item(&mut self.database, |item| item.delete_event(index))?;
+        if let Some(stateful_item) = self.search.get_selected_item_mut() {
+            stateful_item.selected_event = Some(index.saturating_sub(1));
+        }
+        Ok(())
+    }
+
+    /// Replaces the tags of the selected copy, or of the record when no copy is selected.
+    pub fn tag_selected(&mut self, text: &str) -> Result<()> {
+        self.annotate_selected(|annotations| {
+            annotations.set_tags(text);
+            Ok(())
+        })
+    }
+
+    pub fn set_selected_field(&mut self, text: &str) -> Result<()> {
+        let schema = self.custom_fields.clone();
+        self.annotate_selected(|annotations| annotations.set_field(&schema, text))
+    }
+
+    /// Downloads the cover of the selected record, records saved before images were
+    /// kept are looked up on Discogs again.
+    pub fn fetch_selected_cover(&mut self) -> Result<()> {
+        let record = &self
+            .search
+            .list
+            .selected()
+            .ok_or_else(|| AppError::NotFound("Record not selected".to_string()))?
+            .record;
+        if record.is_local() {
+            Err(AppError::Validation(
+                "Only Discogs records have covers".to_string(),
+            ))?
+        }
+        let release;
+        let record = match record.images.is_empty() {
+            true => {
+                release = self.discogs_client.get_release(record.id)?;
+                &release
+            }
+            false => record,
+        };
+        self.cover.store.fetch(record)?;
+        self.cover.reload();
+        Ok(())
+    }
+
+    /// Rates the selected record, an empty text clears the rating.
+    pub fn rate_selected(&mut self, text: &str) -> Result<()> {
+        let rating = ItemHolder::parse_rating(text)?;
+        let holder = self
+            .search
+            .list
+            .selected_mut()
+            .ok_or_else(|| AppError::NotFound("Record not selected".to_string()))?;
+        self.database
+            .review_holder(&holder.record, rating, holder.notes.clone())?;
+        holder.rating = rating;
+        Ok(())
+    }
+
+    pub fn set_selected_notes(&mut self, text: &str) -> Result<()> {
+        let notes = Some(text.trim().to_string()).filter(|n| !n.is_empty());
+        let holder = self
+            .search
+            .list
+            .selected_mut()
+            .ok_or_else(|| AppError::NotFound("Record not selected".to_string()))?;
+        self.database
+            .review_holder(&holder.record, holder.rating, notes.clone())?;
+        holder.notes = notes;
+        Ok(())
+    }
+
+    fn annotate_selected<F>(&mut self, update: F) -> Result<()>
+    where
+        F: FnOnce(&mut Annotations) -> Result<()>,
+    {
+        if self.search.is_item_selected() {
+            return self
+                .search
+                .update_selected_item(&mut self.database, |item| update(&mut item.annotations));
+        }
+        let holder = self
+            .search
+            .list
+            .selected_mut()
+            .ok_or_else(|| AppError::NotFound("Record not selected".to_string()))?;
+        let mut annotations = holder.annotations.clone();
+        update(&mut annotations)?;
+        self.database
+            .annotate_holder(&holder.record, annotations.clone())?;
+        holder.annotations = annotations;
+        Ok(())
+    }
+
+    /// Marks the selected copy as sold, `text` is "price currency; buyer; message".
+    pub fn sell_selected_item(&mut self, text: &str) -> Result<()> {
+        let event = ItemEvent::sale(text)?;
+        self.search
+            .update_selected_item(&mut self.database, |item| item.part_with(event))
+    }
+
+    /// Marks the selected copy as traded away, `text` is "counterparty; received".
+    pub fn trade_selected_item(&mut self, text: &str) -> Result<()> {
+        let event = ItemEvent::trade(text)?;
+        self.search
+            .update_selected_item(&mut self.database, |item| item.part_with(event))
+    }
+
+    /// Refreshes the lists shown on `page` from the active collection.
+    pub fn load_page(&mut self, page: &AppPages) {
+        match page {
+            AppPages::Browse => self.load_locations(),
+            AppPages::OnLoan => self.load_loans(),
+            AppPages::Analytics => self.load_stats(),
+            _ => {}
+        }
+    }
+
+    /// Saves the main input as the contact details of the borrower of the selected loan.
+    pub fn set_loan_contact(&mut self) -> Result<()> {
+        let name = self
+            .loans
+            .selected()
+            .ok_or_else(|| AppError::NotFound("No loan selected".to_string()))?
+            .borrower
+            .clone();
+        let borrower = self
+            .borrowers
+            .set_contact(&name, &self.main_input.lines()[0])?;
+        self.message_box = format!("Contact of {} saved", borrower.name);
+        let selected = self.loans.state.selected();
+        self.load_loans();
+        self.loans.state.select(selected);
+        Ok(())
+    }
+
+    pub fn load_stats(&mut self) {
+        self.stats = collection_stats(self.database.holders());
+    }
+
+    /// Lists the lent copies of the active collection, the most urgent first.
+    pub fn load_loans(&mut self) {
+        let today = Local::now().date_naive();
+        let mut loans: Vec<LoanEntry> = self
+            .database
+            .holders()
+            .iter()
+            .flat_map(|holder| {
+                holder.items.iter().filter_map(|item| {
+                    let loan = item.loan()?;
+                    Some(LoanEntry {
+                        record: holder.record.clone(),
+                        item_id: item.id,
+                        borrower: self.borrowers.name(loan.borrower_id),
+                        contact: loan
+                            .borrower_id
+                            .and_then(|id| self.borrowers.get(id)?.contact.clone()),
+                        since: loan.event.date,
+                        due: loan.due,
+                        overdue: loan.is_overdue(today),
+                    })
+                })
+            })
+            .collect();
+        loans.sort_by_key(|l| (l.due.is_none(), l.due, l.since));
+        self.loans = StatefulList::with_items(loans);
+        self.loans.next();
+    }
+
+    /// Groups the copies of the active collection by the crate they are stored in.
+    pub fn load_locations(&mut self) {
+        let mut groups: BTreeMap<String, Vec<LocationEntry>> = BTreeMap::new();
+        for holder in self.database.holders() {
+            for item in holder.items.iter().filter(|i| i.is_in_collection()) {
+                let (container, slot) = match &item.location {
+                    Some(location) => (location.container(), location.slot.clone()),
+                    None => ("".to_string(), "".to_string()),
+                };
+                groups.entry(container).or_default().push(LocationEntry {
+                    slot,
+                    record: holder.record.clone(),
+                    item_id: item.id,
+                });
+            }
+        }
+        let selected = self.locations.state.selected();
+        self.locations = StatefulList::with_items(
+            groups
+                .into_iter()
+                .map(|(container, mut entries)| {
+                    entries.sort_by(|a, b| a.slot.cmp(&b.slot));
+                    LocationGroup { container, entries }
+                })
+                .collect(),
+        );
+        match selected {
+            Some(index) if index < self.locations.items.len() => {
+                self.locations.state.select(Some(index))
+            }
+            _ => self.locations.next(),
+        }
+    }
+
+    pub fn select_release_from_web_search(&mut self) -> Result<Record> {
+        let index = self.query_results.state.selected();
+        match index {
+            Some(i) if i < self.query_results.items.len() => {
+                let release = &self.query_results.items[i];
+
+                self.discogs_client.get_release(release.id)
+            }
+            _ => Err(AppError::NotFound("No release selected".to_string()))?,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum Navigation {
+    NavigatePage(AppPages),
+    NextCollection,
+    InputSubmit,
+    DoNotihing,
+    QuitInput,
+    QuitSideInput,
+    EnterInput,
+    EnterSideInput,
+    SideInputSubmit,
+    Quit,
+    Combined(Vec<Navigation>),
+}
+
+pub struct LoanEntry {
+    pub record: Record,
+    pub item_id: i64,
+    pub borrower: String,
+    pub contact: Option<String>,
+    pub since: DateTime<Utc>,
+    pub due: Option<NaiveDate>,
+    pub overdue: bool,
+}
+
+pub struct LocationGroup {
+    /// "room/shelf/crate", empty for copies without a location.
+    pub container: String,
+    pub entries: Vec<LocationEntry>,
+}
+
+pub struct LocationEntry {
+    pub slot: String,
+    pub record: Record,
+    pub item_id: i64,
+}
+
+pub struct Collection {
+    pub name: String,
+    /// `None` while the collection is the active one and its database lives in `App`.
+    pub database: Option<Database>,
+}
+
+pub struct Search {
+    pub list: StatefulList<StatefulItemHolder>,
+}
+
+impl Search {
+    pub fn empty() -> Self {
+        Search {
+            list: StatefulList::with_items(vec![]),
+        }
+    }
+
+    pub fn get_selected_item_holder_mut(&mut self) -> Option<&mut StatefulItemHolder> {
+        self.list.selected_mut()
+    }
+
+    #[allow(dead_code)]
+    pub fn get_selected_item_holder(&self) -> Option<&StatefulItemHolder> {
+        self.list.selected()
+    }
+
+    pub fn get_selected_item_mut(&mut self) -> Option<&mut StatefulItem> {
+        self.get_selected_item_holder_mut()
+            .and_then(|ih| ih.list.selected_mut())
+    }
+
+    /// Applies `update` to the selected item through `Database::modify_item`, the list
+    /// is only updated once the change was saved.
+    pub fn update_selected_item<F>(&mut self, database: &mut Database, update: F) -> Result<()>
+    where
+        F: FnOnce(&mut Item) -> Result<()>,
+    {
+        let holder = self
+            .list
+            .selected_mut()
+            .ok_or_else(|| AppError::NotFound("Record not selected".to_string()))?;
+        let stateful_item = holder
+            .list
+            .selected_mut()
+            .ok_or_else(|| AppError::NotFound("Item not selected".to_string()))?;
+        stateful_item.item =
+            database.modify_item(holder.record.id, stateful_item.item.id, update)?;
+        Ok(())
+    }
+
+    pub fn is_item_selected(&mut self) -> bool {
+        self.get_selected_item_mut().is_some()
+    }
+
+    /// What the side input is used for, the selected copy takes precedence over the record.
+    pub fn selected_input(&mut self) -> Option<ItemInput> {
+        let holder = self.list.selected_mut()?;
+        match holder.list.selected_mut() {
+            Some(item) => item.input.clone(),
+            None => holder.input.clone(),
+        }
+    }
+}
+
+impl From<AppPages> for usize {
+    fn from(val: AppPages) -> Self {
+        val as usize
+    }
+}
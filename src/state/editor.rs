@@ -0,0 +1,511 @@
+use tui_textarea::TextArea;
+
+use record_catalog::models::{
+    error::{AppError, Result},
+    record::{Artist, Format, Identifier, Label, Record, Track},
+};
+
+pub struct EditorField<'a> {
+    pub title: &'static str,
+    pub hint: &'static str,
+    pub multiline: bool,
+    pub input: TextArea<'a>,
+}
+
+impl EditorField<'_> {
+    fn new(title: &'static str, hint: &'static str, multiline: bool) -> Self {
+        EditorField {
+            title,
+            hint,
+            multiline,
+            input: TextArea::default(),
+        }
+    }
+
+    fn with_lines(mut self, lines: Vec<String>) -> Self {
+        if !lines.is_empty() {
+            self.input = TextArea::new(lines);
+        }
+        self
+    }
+
+    pub fn text(&self) -> String {
+        self.input.lines().join("\n")
+    }
+
+    fn non_empty_lines(&self) -> Vec<String> {
+        self.input
+            .lines()
+            .iter()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect()
+    }
+
+    fn split_lines(&self, name: &str) -> Result<Vec<(String, String)>> {
+        self.non_empty_lines()
+            .into_iter()
+            .map(|l| match l.split_once('|') {
+                Some((a, b)) => Ok((a.trim().to_string(), b.trim().to_string())),
+                None => Err(AppError::Validation(format!(
+                    "{} line \"{}\" is missing a \"|\"",
+                    name, l
+                ))),
+            })
+            .collect()
+    }
+}
+
+pub struct Editor<'a> {
+    pub fields: Vec<EditorField<'a>>,
+    pub selected: usize,
+    pub is_input: bool,
+    /// The stored record being edited, `None` when entering a new one.
+    pub target: Option<Record>,
+}
+
+impl<'a> Editor<'a> {
+    const TITLE: usize = 0;
+    const ARTISTS: usize = 1;
+    const YEAR: usize = 2;
+    const RELEASED: usize = 3;
+    const COUNTRY: usize = 4;
+    const FORMATS: usize = 5;
+    const LABELS: usize = 6;
+    const IDENTIFIERS: usize = 7;
+    const GENRES: usize = 8;
+    const STYLES: usize = 9;
+    const TRACKLIST: usize = 10;
+    const CREDITS: usize = 11;
+    const NOTES: usize = 12;
+
+    pub fn empty() -> Self {
+        Editor {
+            fields: vec![
+                EditorField::new("Title", "Release title", false),
+                EditorField::new("Artists", "One artist per line: name | role", true),
+                EditorField::new("Year", "Release year, leave empty if unknown", false),
+                EditorField::new(
+                    "Released",
+                    "Release date, unknown parts zeroed, e.g. 1977-02-00",
+                    false,
+                ),
+                EditorField::new("Country", "Country of release", false),
+                EditorField::new(
+                    "Formats",
+                    "One format per line: name | quantity | descriptions, e.g. Vinyl | 2 | LP, Album",
+                    true,
+                ),
+                EditorField::new("Labels", "One label per line: name | catno", true),
+                EditorField::new("Identifiers", "One per line: type | value", true),
+                EditorField::new("Genres", "Comma separated", false),
+                EditorField::new("Styles", "Comma separated", false),
+                EditorField::new(
+                    "Tracklist",
+                    "One track per line: position | title | duration | Artist (Role); ... \
+                     Prefix sub tracks with \"-\" and other kinds of lines with their \
+                     type, e.g. \"[heading] | Side A |\"",
+                    true,
+                ),
+                EditorField::new(
+                    "Credits",
+                    "One credit per line: name | role | tracks, leave tracks empty for all",
+                    true,
+                ),
+                EditorField::new("Notes", "Release notes", true),
+            ],
+            selected: 0,
+            is_input: false,
+            target: None,
+        }
+    }
+
+    pub fn from_record(record: &Record) -> Self {
+        let mut editor = Editor::empty();
+        let year = match record.year {
+            0 => "".to_string(),
+            y => y.to_string(),
+        };
+        let values = vec![
+            vec![record.title.clone()],
+            record
+                .artists
+                .iter()
+                .map(|a| match a.role.as_str() {
+                    "" => a.name.clone(),
+                    role => format!("{} | {}", a.name, role),
+                })
+                .collect(),
+            vec![year],
+            record.released.iter().cloned().collect(),
+            record.country.iter().cloned().collect(),
+            record.formats.iter().map(format_line).collect(),
+            record
+                .labels
+                .iter()
+                .map(|l| format!("{} | {}", l.name, l.catno))
+                .collect(),
+            record
+                .identifiers
+                .iter()
+                .map(|i| format!("{} | {}", i.type_, i.value))
+                .collect(),
+            vec![record.genres.join(", ")],
+            vec![record.styles.join(", ")],
+            record
+                .tracklist
+                .iter()
+                .flat_map(|t| {
+                    let mut lines = vec![track_line(t)];
+                    if let Some(sub_tracks) = &t.sub_tracks {
+                        lines.extend(sub_tracks.iter().map(|s| "- ".to_string() + &track_line(s)));
+                    }
+                    lines
+                })
+                .collect(),
+            record
+                .extraartists
+                .iter()
+                .map(|a| match a.tracks.as_deref() {
+                    None | Some("") => format!("{} | {}", a.name, a.role),
+                    Some(tracks) => format!("{} | {} | {}", a.name, a.role, tracks),
+                })
+                .collect(),
+            record
+                .notes
+                .iter()
+                .flat_map(|n| n.lines().map(String::from))
+                .collect(),
+        ];
+        editor.fields = editor
+            .fields
+            .into_iter()
+            .zip(values)
+            .map(|(f, v)| f.with_lines(v))
+            .collect();
+        editor.target = Some(record.clone());
+        editor
+    }
+
+    pub fn selected_field(&self) -> &EditorField<'a> {
+        &self.fields[self.selected]
+    }
+
+    pub fn selected_field_mut(&mut self) -> &mut EditorField<'a> {
+        &mut self.fields[self.selected]
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % self.fields.len();
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = (self.selected + self.fields.len() - 1) % self.fields.len();
+    }
+
+    /// Builds a record out of the form contents, validating every field.
+    /// Everything the form doesn't show (ids) is kept from `base`.
+    pub fn to_record(&self, base: &Record) -> Result<Record> {
+        let title = self.fields[Self::TITLE].text().trim().to_string();
+        if title.is_empty() {
+            Err(AppError::Validation("Title can't be empty".to_string()))?
+        }
+
+        let year = match self.fields[Self::YEAR].text().trim() {
+            "" => 0,
+            y => y
+                .parse::<i32>()
+                .map_err(|_| AppError::Validation(format!("Year \"{}\" is not a number", y)))?,
+        };
+
+        let known_artists: Vec<&Artist> = base
+            .artists
+            .iter()
+            .chain(&base.extraartists)
+            .chain(
+                base.tracklist
+                    .iter()
+                    .flat_map(|t| t.extraartists.iter().flatten()),
+            )
+            .collect();
+        let artist = |name: String, role: String| Artist {
+            id: known_artists
+                .iter()
+                .find(|a| a.name == name)
+                .map_or(0, |a| a.id),
+            name,
+            role,
+            tracks: None,
+        };
+
+        let mut tracklist: Vec<Track> = vec![];
+        for line in self.fields[Self::TRACKLIST].non_empty_lines() {
+            let (is_sub_track, line) = match line.strip_prefix('-') {
+                Some(rest) => (true, rest.trim().to_string()),
+                None => (false, line),
+            };
+            let (type_, line) = match line.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+                Some((type_, rest)) => (type_.trim().to_string(), rest.trim().to_string()),
+                None => ("track".to_string(), line),
+            };
+            let mut parts = line.split('|').map(|p| p.trim().to_string());
+            let position = parts.next().unwrap_or_default();
+            let title = parts.next().unwrap_or_default();
+            let duration = parts.next().unwrap_or_default();
+            let extraartists = parse_credits(&parts.next().unwrap_or_default())?
+                .into_iter()
+                .map(|(name, role)| artist(name, role))
+                .collect::<Vec<_>>();
+            if title.is_empty() {
+                Err(AppError::Validation(format!(
+                    "Track \"{}\" has no title",
+                    line
+                )))?
+            }
+            let track = Track {
+                type_,
+                position,
+                title,
+                duration,
+                sub_tracks: None,
+                extraartists: match extraartists.is_empty() {
+                    true => None,
+                    false => Some(extraartists),
+                },
+            };
+
+            if is_sub_track {
+                tracklist
+                    .last_mut()
+                    .ok_or_else(|| {
+                        AppError::Validation(format!(
+                            "Sub track \"{}\" has no parent track",
+                            track.title
+                        ))
+                    })?
+                    .sub_tracks
+                    .get_or_insert_with(Vec::new)
+                    .push(track);
+            } else {
+                tracklist.push(track);
+            }
+        }
+
+        let mut record = base.clone();
+        record.title = title;
+        record.year = year;
+        record.formats = self.fields[Self::FORMATS]
+            .non_empty_lines()
+            .iter()
+            .map(|line| parse_format(line))
+            .collect();
+        record.artists = self.fields[Self::ARTISTS]
+            .non_empty_lines()
+            .into_iter()
+            .map(|l| match l.split_once('|') {
+                Some((name, role)) => artist(name.trim().to_string(), role.trim().to_string()),
+                None => artist(l, "".to_string()),
+            })
+            .collect();
+        record.labels = self.fields[Self::LABELS]
+            .split_lines("Label")?
+            .into_iter()
+            .map(|(name, catno)| Label { name, catno })
+            .collect();
+        record.identifiers = self.fields[Self::IDENTIFIERS]
+            .split_lines("Identifier")?
+            .into_iter()
+            .map(|(type_, value)| Identifier { type_, value })
+            .collect();
+        record.genres = split_commas(&self.fields[Self::GENRES].text());
+        record.styles = split_commas(&self.fields[Self::STYLES].text());
+        record.tracklist = tracklist;
+        record.released = non_empty(self.fields[Self::RELEASED].text());
+        record.country = non_empty(self.fields[Self::COUNTRY].text());
+        record.extraartists = self.fields[Self::CREDITS]
+            .non_empty_lines()
+            .into_iter()
+            .map(|l| {
+                let mut parts = l.splitn(3, '|').map(|p| p.trim().to_string());
+                let name = parts.next().unwrap_or_default();
+                let role = parts.next().unwrap_or_default();
+                Artist {
+                    tracks: parts.next().filter(|t| !t.is_empty()),
+                    ..artist(name, role)
+                }
+            })
+            .collect();
+        record.notes = non_empty(self.fields[Self::NOTES].text());
+        Ok(record)
+    }
+}
+
+/// "Vinyl | 2 | LP, Album", only the name when there are no details.
+fn format_line(format: &Format) -> String {
+    match (&format.qty, format.descriptions.is_empty()) {
+        (None, true) => format.name.clone(),
+        (qty, _) => format!(
+            "{} | {} | {}",
+            format.name,
+            qty.as_deref().unwrap_or(""),
+            format.descriptions.join(", ")
+        ),
+    }
+}
+
+fn parse_format(line: &str) -> Format {
+    let mut parts = line.splitn(3, '|').map(|p| p.trim());
+    Format {
+        name: parts.next().unwrap_or_default().to_string(),
+        qty: parts
+            .next()
+            .filter(|qty| !qty.is_empty())
+            .map(|qty| qty.to_string()),
+        descriptions: split_commas(parts.next().unwrap_or_default()),
+    }
+}
+
+/// Track lines start with their type unless they are plain tracks, e.g. "[heading]".
+fn track_line(track: &Track) -> String {
+    let mut line = match track.type_.as_str() {
+        "track" | "" => "".to_string(),
+        type_ => format!("[{}] ", type_),
+    };
+    line += &format!("{} | {} | {}", track.position, track.title, track.duration);
+    if let Some(artists) = &track.extraartists {
+        line += " | ";
+        line += &artists
+            .iter()
+            .map(|a| match a.role.as_str() {
+                "" => a.name.clone(),
+                role => format!("{} ({})", a.name, role),
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+    }
+    line
+}
+
+/// Parses "Name (Role); Other Name" into name and role pairs.
+fn parse_credits(text: &str) -> Result<Vec<(String, String)>> {
+    text.split(';')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .map(|credit| match credit.split_once(" (") {
+            Some((name, role)) => match role.strip_suffix(')') {
+                Some(role) => Ok((name.trim().to_string(), role.trim().to_string())),
+                None => Err(AppError::Validation(format!(
+                    "Credit \"{}\" is missing a \")\"",
+                    credit
+                ))),
+            },
+            None => Ok((credit, "".to_string())),
+        })
+        .collect()
+}
+
+fn non_empty(text: String) -> Option<String> {
+    Some(text.trim().to_string()).filter(|t| !t.is_empty())
+}
+
+fn split_commas(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artist(name: &str, role: &str, id: i64, tracks: Option<&str>) -> Artist {
+        Artist {
+            name: name.to_string(),
+            role: role.to_string(),
+            id,
+            tracks: tracks.map(String::from),
+        }
+    }
+
+    fn track(type_: &str, position: &str, title: &str, credits: Option<Vec<Artist>>) -> Track {
+        Track {
+            position: position.to_string(),
+            type_: type_.to_string(),
+            title: title.to_string(),
+            duration: "".to_string(),
+            sub_tracks: None,
+            extraartists: credits,
+        }
+    }
+
+    #[test]
+    fn records_survive_the_editor() {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        let mut suite = track("index", "B1", "Suite", None);
+        suite.sub_tracks = Some(vec![track("track", "B1a", "Part One", None)]);
+        let record = Record {
+            id: 249504,
+            year: 1977,
+            title: "Rumours".to_string(),
+            formats: vec![Format {
+                name: "Vinyl".to_string(),
+                qty: Some("1".to_string()),
+                descriptions: strings(&["LP", "Album"]),
+            }],
+            artists: vec![artist("Fleetwood Mac", "", 1, None)],
+            labels: vec![Label {
+                name: "Warner Bros. Records".to_string(),
+                catno: "BSK 3010".to_string(),
+            }],
+            identifiers: vec![Identifier {
+                type_: "Barcode".to_string(),
+                value: "0 7599-27313-1 5".to_string(),
+            }],
+            genres: strings(&["Rock"]),
+            styles: strings(&["Soft Rock", "Pop Rock"]),
+            tracklist: vec![
+                track("heading", "", "Side A", None),
+                track(
+                    "track",
+                    "A1",
+                    "Second Hand News",
+                    Some(vec![artist("Lindsey Buckingham", "Vocals", 2, None)]),
+                ),
+                suite,
+            ],
+            country: Some("US".to_string()),
+            released: Some("1977-02-04".to_string()),
+            notes: Some("Gatefold sleeve.\nWith inner sleeve.".to_string()),
+            extraartists: vec![
+                artist("Ken Caillat", "Producer", 3, None),
+                artist("Lindsey Buckingham", "Guitar", 2, Some("A1 to B1")),
+            ],
+            ..Record::default()
+        };
+        let edited = Editor::from_record(&record).to_record(&record).unwrap();
+        assert_eq!(edited, record);
+    }
+
+    #[test]
+    fn emptied_fields_are_cleared() {
+        let record = Record {
+            title: "Rumours".to_string(),
+            country: Some("US".to_string()),
+            notes: Some("Gatefold".to_string()),
+            extraartists: vec![artist("Ken Caillat", "Producer", 3, None)],
+            ..Record::default()
+        };
+        let mut editor = Editor::from_record(&record);
+        for field in [Editor::COUNTRY, Editor::NOTES, Editor::CREDITS] {
+            editor.fields[field].input = TextArea::default();
+        }
+        let edited = editor.to_record(&record).unwrap();
+        assert_eq!(edited.country, None);
+        assert_eq!(edited.notes, None);
+        assert!(edited.extraartists.is_empty());
+
+        editor.fields[Editor::TITLE].input = TextArea::default();
+        assert!(editor.to_record(&record).is_err());
+    }
+}
@@ -8,14 +8,11 @@ pub struct StatefulList<T> {
 impl<T> StatefulList<T> {
     pub fn with_items(items: Vec<T>) -> StatefulList<T> {
         let state = ListState::default();
-        StatefulList {
-            state: state,
-            items,
-        }
+        StatefulList { state, items }
     }
 
     pub fn next(&mut self) {
-        if self.items.len() == 0 {
+        if self.items.is_empty() {
             self.unselect();
             return;
         }
@@ -33,7 +30,7 @@ impl<T> StatefulList<T> {
     }
 
     pub fn previous(&mut self) {
-        if self.items.len() == 0 {
+        if self.items.is_empty() {
             self.unselect();
             return;
         }
@@ -56,25 +53,23 @@ impl<T> StatefulList<T> {
     }
 
     pub fn selected_mut(&mut self) -> Option<&mut T> {
-        self.state
-            .selected()
-            .map_or(None, |index| Some(&mut self.items[index]))
+        self.items.get_mut(self.state.selected()?)
     }
 
     pub fn selected(&self) -> Option<&T> {
-        self.state
-            .selected()
-            .map_or(None, |index| Some(&self.items[index]))
+        self.items.get(self.state.selected()?)
     }
 
     pub fn update_selected(&mut self, item: T) {
-        if let Some(index) = self.state.selected() {
-            self.items[index] = item;
+        if let Some(selected) = self.selected_mut() {
+            *selected = item;
         }
     }
 
     pub fn remove_at_index(&mut self, index: usize) {
-        self.items.remove(index);
-        self.previous();
+        if index < self.items.len() {
+            self.items.remove(index);
+            self.previous();
+        }
     }
 }
@@ -0,0 +1,4 @@
+pub mod app;
+pub mod editor;
+pub mod list;
+pub mod stateful;
@@ -0,0 +1,77 @@
+use record_catalog::models::{
+    annotations::Annotations,
+    item_holder::{Item, ItemEventType, ItemHolder},
+    record::Record,
+};
+use serde::{Deserialize, Serialize};
+
+use super::list::StatefulList;
+
+/// A record in the search results with the list and input state of its copies.
+pub struct StatefulItemHolder {
+    pub record: Record,
+    pub annotations: Annotations,
+    pub rating: Option<u8>,
+    pub notes: Option<String>,
+    pub detail_offset: usize,
+    pub list: StatefulList<StatefulItem>,
+    /// Side input used on the record itself while no copy is selected.
+    pub input: Option<ItemInput>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct StatefulItem {
+    pub item: Item,
+    pub input: Option<ItemInput>,
+    /// Index into `item.events` of the event selected in the timeline.
+    #[serde(default)]
+    pub selected_event: Option<usize>,
+    /// Scroll position of the event timeline, in lines.
+    #[serde(default)]
+    pub detail_offset: usize,
+}
+
+/// What the side input is currently being used for.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ItemInput {
+    Event(ItemEventType),
+    Grade,
+    Acquisition,
+    Location,
+    Lend,
+    Transfer,
+    Sale,
+    Trade,
+    EditEvent,
+    Tags,
+    Field,
+    Rating,
+    Notes,
+}
+
+impl From<ItemHolder> for StatefulItemHolder {
+    fn from(holder: ItemHolder) -> Self {
+        StatefulItemHolder {
+            record: holder.record,
+            annotations: holder.annotations,
+            rating: holder.rating,
+            notes: holder.notes,
+            list: StatefulList::with_items(
+                holder.items.into_iter().map(StatefulItem::from).collect(),
+            ),
+            detail_offset: 0,
+            input: None,
+        }
+    }
+}
+
+impl From<Item> for StatefulItem {
+    fn from(item: Item) -> Self {
+        StatefulItem {
+            item,
+            input: None,
+            selected_event: None,
+            detail_offset: 0,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Totals and breakdowns of the collection.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{Datelike, Local};
+use serde::Serialize;
+
+use crate::models::item_holder::{Item, ItemEventType, ItemHolder, ItemState};
+
+/// Counts of the copies still in the collection.
+#[derive(Default, Debug, Serialize)]
+pub struct CollectionStats {
+    pub holders: usize,
+    pub copies: usize,
+    pub on_loan: usize,
+    /// Largest count first.
+    pub by_genre: Vec<(String, u64)>,
+    pub by_style: Vec<(String, u64)>,
+    pub by_format: Vec<(String, u64)>,
+    pub by_label: Vec<(String, u64)>,
+    pub top_artists: Vec<(String, u64)>,
+    /// Release decades in chronological order, e.g. "1970s".
+    pub by_decade: Vec<(String, u64)>,
+    /// Size of the collection at the end of each year in which copies came or went,
+    /// including the ones sold, traded or lost since.
+    pub growth: Vec<(String, u64)>,
+}
+
+/// Counts and breakdowns of the copies in the collection.
+pub fn collection_stats(data: &[ItemHolder]) -> CollectionStats {
+    let mut stats = CollectionStats::default();
+    let mut genres = HashMap::new();
+    let mut styles = HashMap::new();
+    let mut formats = HashMap::new();
+    let mut labels = HashMap::new();
+    let mut artists = HashMap::new();
+    let mut decades = BTreeMap::new();
+    let mut changes = BTreeMap::new();
+
+    for holder in data {
+        for item in &holder.items {
+            membership_changes(item, &mut changes);
+        }
+        let copies: Vec<_> = holder
+            .items
+            .iter()
+            .filter(|i| i.is_in_collection())
+            .collect();
+        if copies.is_empty() {
+            continue;
+        }
+        let count = copies.len() as u64;
+        let record = &holder.record;
+        stats.holders += 1;
+        stats.copies += copies.len();
+        stats.on_loan += copies
+            .iter()
+            .filter(|i| i.state() == ItemState::OnLoan)
+            .count();
+
+        for genre in &record.genres {
+            *genres.entry(genre.clone()).or_default() += count;
+        }
+        for style in &record.styles {
+            *styles.entry(style.clone()).or_default() += count;
+        }
+        for format in &record.formats {
+            *formats.entry(format.name.clone()).or_default() += count;
+        }
+        for label in &record.labels {
+            *labels.entry(label.name.clone()).or_default() += count;
+        }
+        for artist in &record.artists {
+            *artists.entry(artist.name.clone()).or_default() += count;
+        }
+        if record.year > 0 {
+            *decades
+                .entry(format!("{}s", record.year / 10 * 10))
+                .or_default() += count;
+        }
+    }
+
+    stats.by_genre = ranked(genres);
+    stats.by_style = ranked(styles);
+    stats.by_format = ranked(formats);
+    stats.by_label = ranked(labels);
+    stats.top_artists = ranked(artists);
+    stats.by_decade = decades.into_iter().collect();
+    stats.growth = changes
+        .into_iter()
+        .scan(0i64, |total, (year, change)| {
+            *total += change;
+            Some((year.to_string(), (*total).max(0) as u64))
+        })
+        .collect();
+    stats
+}
+
+/// Adds one for the year a copy came into the collection and takes one off for the
+/// year it left, following its events like [`Item::state`]. Copies on loan still count.
+fn membership_changes(item: &Item, changes: &mut BTreeMap<i32, i64>) {
+    let mut present = false;
+    for event in &item.events {
+        let now_present = match event.event_type {
+            ItemEventType::Created
+            | ItemEventType::TransferredIn
+            | ItemEventType::Returned
+            | ItemEventType::Lent => true,
+            ItemEventType::Sold
+            | ItemEventType::Traded
+            | ItemEventType::Lost
+            | ItemEventType::TransferredOut => false,
+            _ => present,
+        };
+        if now_present != present {
+            let year = event.date.with_timezone(&Local).year();
+            *changes.entry(year).or_default() += if now_present { 1 } else { -1 };
+            present = now_present;
+        }
+    }
+}
+
+/// Sorts by count, ties by name.
+fn ranked(counts: HashMap<String, u64>) -> Vec<(String, u64)> {
+    let mut ranked: Vec<_> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::models::{item_holder::ItemEvent, record::Record};
+
+    fn event(event_type: ItemEventType, year: i32) -> ItemEvent {
+        let mut event = ItemEvent::new(event_type);
+        event.date = Utc.with_ymd_and_hms(year, 6, 1, 12, 0, 0).unwrap();
+        event
+    }
+
+    #[test]
+    fn growth_counts_copies_that_left_since() {
+        let mut holder = ItemHolder::new(Record::default());
+        for (id, sold) in [(1, Some(2021)), (2, None)] {
+            let mut item = Item::new_with_id(id);
+            item.events = vec![event(ItemEventType::Created, 2019 + id as i32)];
+            if let Some(year) = sold {
+                item.events.push(event(ItemEventType::Sold, year));
+            }
+            holder.items.push(item);
+        }
+
+        let stats = collection_stats(&[holder]);
+        let growth: Vec<_> = stats.growth.iter().map(|(y, n)| (y.as_str(), *n)).collect();
+        assert_eq!(growth, vec![("2020", 1), ("2021", 1)]);
+        assert_eq!(stats.copies, 1);
+    }
+}
@@ -0,0 +1,394 @@
+//! Value of the collection from marketplace prices.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+};
+
+use chrono::{serde::ts_seconds, DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{
+    error::{AppError, Result},
+    item_holder::{Grade, ItemHolder},
+};
+
+/// An amount in hundredths of its currency, e.g. cents.
+pub type Cents = i64;
+
+/// Amounts keyed by currency code, amounts in different currencies are never added up.
+pub type Amounts = BTreeMap<String, Cents>;
+
+/// Rounds a decimal amount to cents.
+pub fn to_cents(value: f64) -> Cents {
+    (value * 100.0).round() as Cents
+}
+
+/// "12.50 EUR, 3.00 USD", or "-" when there is nothing.
+pub fn format_amounts(amounts: &Amounts) -> String {
+    match amounts.is_empty() {
+        true => "-".to_string(),
+        false => amounts
+            .iter()
+            .map(|(currency, cents)| format_cents(*cents, currency))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// "12.50 EUR".
+pub fn format_cents(cents: Cents, currency: &str) -> String {
+    format!("{} {}", format_decimal(cents), currency)
+}
+
+/// "12.50", without a currency.
+pub fn format_decimal(cents: Cents) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100)
+}
+
+/// Reads a decimal amount typed by the user, e.g. "12.50".
+pub fn parse_cents(text: &str) -> Result<Cents> {
+    text.trim()
+        .parse()
+        .ok()
+        .filter(|v: &f64| v.is_finite())
+        .map(to_cents)
+        .ok_or_else(|| AppError::Validation(format!("Invalid price \"{}\"", text)))
+}
+
+/// An amount in a currency.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Price {
+    pub currency: String,
+    /// Discogs sends prices as decimal numbers, they are kept in cents.
+    #[serde(rename = "value", with = "decimal_cents")]
+    pub cents: Cents,
+}
+
+/// Stores cents as a decimal number, as Discogs sends prices and older files hold them.
+pub mod decimal_cents {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::{to_cents, Cents};
+
+    /// Writes the amount as a decimal number.
+    pub fn serialize<S: Serializer>(cents: &Cents, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(*cents as f64 / 100.0)
+    }
+
+    /// Reads a decimal number rounded to cents.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Cents, D::Error> {
+        Ok(to_cents(f64::deserialize(deserializer)?))
+    }
+}
+
+/// Suggested prices keyed by the Discogs condition name, e.g. "Very Good Plus (VG+)".
+pub type PriceSuggestions = HashMap<String, Price>;
+
+/// What the Discogs marketplace currently offers for a release.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct MarketplaceStats {
+    pub lowest_price: Option<Price>,
+    pub num_for_sale: Option<i64>,
+    #[serde(default)]
+    pub blocked_from_sale: bool,
+}
+
+/// Where release prices come from, implemented by `DiscogsClient`.
+pub trait PriceSource {
+    fn price_suggestions(&self, release_id: i64) -> Result<PriceSuggestions>;
+    fn marketplace_stats(&self, release_id: i64) -> Result<MarketplaceStats>;
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+struct CachedPrices {
+    #[serde(with = "ts_seconds")]
+    fetched: DateTime<Utc>,
+    suggestions: PriceSuggestions,
+    stats: Option<MarketplaceStats>,
+}
+
+/// Prices fetched from a `PriceSource`, persisted so the marketplace isn't queried
+/// for every release each time the collection is valued.
+pub struct PriceCache {
+    file_path: String,
+    max_age: Duration,
+    entries: HashMap<i64, CachedPrices>,
+}
+
+impl PriceCache {
+    /// Loads the cache from `file_path`, starting empty when the file doesn't exist yet.
+    pub fn new(file_path: &str) -> Result<Self> {
+        let entries = match fs::read_to_string(file_path) {
+            Ok(data) => serde_json::from_str(&data)?,
+            Err(_) => HashMap::new(),
+        };
+        Ok(PriceCache {
+            file_path: file_path.to_string(),
+            max_age: Duration::days(7),
+            entries,
+        })
+    }
+
+    /// Writes the cache back to its file.
+    pub fn save(&self) -> Result<()> {
+        fs::write(&self.file_path, serde_json::to_string(&self.entries)?)
+            .map_err(|e| AppError::file(&self.file_path, e))?;
+        Ok(())
+    }
+
+    /// Value of a single copy: the suggestion for its media grade,
+    /// or the lowest marketplace price when it isn't graded.
+    pub fn value_of(
+        &mut self,
+        source: &dyn PriceSource,
+        release_id: i64,
+        grade: Option<Grade>,
+    ) -> Result<Option<Price>> {
+        let prices = self.get(source, release_id)?;
+        Ok(match grade {
+            Some(grade) => prices.suggestions.get(grade.discogs_name()).cloned(),
+            None => None,
+        }
+        .or_else(|| prices.stats.as_ref()?.lowest_price.clone()))
+    }
+
+    fn is_fresh(&self, release_id: i64) -> bool {
+        self.entries
+            .get(&release_id)
+            .is_some_and(|p| Utc::now() - p.fetched < self.max_age)
+    }
+
+    /// Fetches the suggestions and marketplace stats of a stale release. Either is enough,
+    /// suggestions are refused to accounts without seller settings. Releases with
+    /// neither are cached as well so they aren't asked for again on every run.
+    fn get(&mut self, source: &dyn PriceSource, release_id: i64) -> Result<&CachedPrices> {
+        if !self.is_fresh(release_id) {
+            let suggestions = source.price_suggestions(release_id);
+            let stats = source.marketplace_stats(release_id);
+            let prices = match (suggestions, stats) {
+                (Err(AppError::NotFound(_)), Err(AppError::NotFound(_))) => CachedPrices {
+                    fetched: Utc::now(),
+                    suggestions: PriceSuggestions::new(),
+                    stats: None,
+                },
+                (Err(AppError::NotFound(_)), Err(e)) | (Err(e), Err(_)) => return Err(e),
+                (suggestions, stats) => CachedPrices {
+                    fetched: Utc::now(),
+                    suggestions: suggestions.unwrap_or_default(),
+                    stats: stats.ok(),
+                },
+            };
+            self.entries.insert(release_id, prices);
+        }
+        Ok(&self.entries[&release_id])
+    }
+}
+
+/// Value of the copies in the collection, per currency.
+#[derive(Default, Debug)]
+pub struct CollectionValue {
+    pub total: Amounts,
+    pub by_genre: BTreeMap<String, Amounts>,
+    pub by_format: BTreeMap<String, Amounts>,
+    pub by_label: BTreeMap<String, Amounts>,
+    /// What was paid for the copies, per purchase currency.
+    pub paid: Amounts,
+    /// Number of priced copies per currency.
+    pub priced_in: BTreeMap<String, usize>,
+    pub priced: usize,
+    pub unpriced: usize,
+    /// Copies whose prices couldn't be fetched, see `error`.
+    pub failed: usize,
+    /// Why prices couldn't be fetched, the first error is kept.
+    pub error: Option<String>,
+}
+
+impl CollectionValue {
+    /// The currency most copies were priced in, used to order the breakdowns.
+    pub fn main_currency(&self) -> Option<&str> {
+        self.priced_in
+            .iter()
+            .max_by_key(|(_, count)| **count)
+            .map(|(currency, _)| currency.as_str())
+    }
+}
+
+/// Values every copy still in the collection. Copies of manually entered records
+/// or releases without any price count as unpriced, copies whose prices couldn't be
+/// fetched as failed. After a network error no more prices are fetched, copies of
+/// releases that aren't cached count as failed then.
+pub fn collection_value(
+    data: &[ItemHolder],
+    source: &dyn PriceSource,
+    cache: &mut PriceCache,
+) -> Result<CollectionValue> {
+    let mut value = CollectionValue::default();
+    let mut offline = false;
+
+    for holder in data {
+        for item in holder.items.iter().filter(|i| i.is_in_collection()) {
+            if let Some(acquisition) = &item.acquisition {
+                *value.paid.entry(acquisition.currency.clone()).or_default() += acquisition.price;
+            }
+
+            let record = &holder.record;
+            let price = match record.is_local() {
+                true => None,
+                false if offline && !cache.is_fresh(record.id) => {
+                    value.failed += 1;
+                    continue;
+                }
+                false => match cache.value_of(source, record.id, item.media_condition) {
+                    Ok(price) => price,
+                    Err(AppError::NotFound(_)) => None,
+                    Err(e) => {
+                        offline |= matches!(e, AppError::Network(_));
+                        value.failed += 1;
+                        value.error.get_or_insert(e.to_string());
+                        continue;
+                    }
+                },
+            };
+            let price = match price {
+                Some(price) => price,
+                None => {
+                    value.unpriced += 1;
+                    continue;
+                }
+            };
+
+            value.priced += 1;
+            *value.priced_in.entry(price.currency.clone()).or_default() += 1;
+            let add = |amounts: &mut Amounts| {
+                *amounts.entry(price.currency.clone()).or_default() += price.cents;
+            };
+            add(&mut value.total);
+            for genre in &record.genres {
+                add(value.by_genre.entry(genre.clone()).or_default());
+            }
+            for format in &record.formats {
+                add(value.by_format.entry(format.name.clone()).or_default());
+            }
+            for label in &record.labels {
+                add(value.by_label.entry(label.name.clone()).or_default());
+            }
+        }
+    }
+
+    cache.save()?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::models::{item_holder::Item, record::Record};
+
+    /// Prices for a few made up releases, recording which ones were asked for.
+    #[derive(Default)]
+    struct StubSource {
+        requested: RefCell<Vec<i64>>,
+    }
+
+    impl PriceSource for StubSource {
+        fn price_suggestions(&self, release_id: i64) -> Result<PriceSuggestions> {
+            self.requested.borrow_mut().push(release_id);
+            let price = |currency: &str, cents| Price {
+                currency: currency.to_string(),
+                cents,
+            };
+            match release_id {
+                1 => Ok(HashMap::from([(
+                    Grade::NearMint.discogs_name().to_string(),
+                    price("EUR", 1250),
+                )])),
+                2 => Ok(HashMap::new()),
+                3 => Err(AppError::Network("Too many requests".to_string())),
+                6 => Err(AppError::Network(
+                    "Discogs rejected the request".to_string(),
+                )),
+                _ => Err(AppError::NotFound("Not found on Discogs".to_string())),
+            }
+        }
+
+        fn marketplace_stats(&self, release_id: i64) -> Result<MarketplaceStats> {
+            match release_id {
+                3 => Err(AppError::Network("Too many requests".to_string())),
+                4 | 5 => Err(AppError::NotFound("Not found on Discogs".to_string())),
+                _ => Ok(MarketplaceStats {
+                    lowest_price: Some(Price {
+                        currency: "USD".to_string(),
+                        cents: 100 * release_id + 10,
+                    }),
+                    num_for_sale: Some(1),
+                    blocked_from_sale: false,
+                }),
+            }
+        }
+    }
+
+    fn holder(id: i64, grade: Option<Grade>) -> ItemHolder {
+        let mut holder = ItemHolder::new(Record {
+            id,
+            genres: vec!["Rock".to_string()],
+            ..Record::default()
+        });
+        let mut item = Item::new_with_id(1);
+        item.media_condition = grade;
+        holder.items.push(item);
+        holder
+    }
+
+    #[test]
+    fn totals_are_kept_per_currency_and_failures_counted() {
+        let data = vec![
+            holder(1, Some(Grade::NearMint)),
+            holder(2, None),
+            holder(5, None),
+            holder(6, Some(Grade::NearMint)),
+            holder(-1, None),
+            holder(3, None),
+            holder(4, None),
+        ];
+        let source = StubSource::default();
+        let path = std::env::temp_dir().join(format!("prices-test-{}.json", std::process::id()));
+        let mut cache = PriceCache::new(path.to_str().unwrap()).unwrap();
+
+        let value = collection_value(&data, &source, &mut cache).unwrap();
+
+        let amounts = |pairs: &[(&str, Cents)]| -> Amounts {
+            pairs.iter().map(|(c, v)| (c.to_string(), *v)).collect()
+        };
+        // Release 6 has no suggestions but a lowest price.
+        assert_eq!(value.total, amounts(&[("EUR", 1250), ("USD", 820)]));
+        assert_eq!(value.by_genre["Rock"], value.total);
+        assert_eq!(value.priced, 3);
+        assert_eq!(value.unpriced, 2);
+        assert_eq!(value.failed, 2);
+        assert_eq!(value.error.as_deref(), Some("Too many requests"));
+        // Nothing is fetched after a network error.
+        assert_eq!(*source.requested.borrow(), vec![1, 2, 5, 6, 3]);
+
+        // Releases without any price are cached like the others.
+        let source = StubSource::default();
+        let mut cache = PriceCache::new(path.to_str().unwrap()).unwrap();
+        collection_value(&data, &source, &mut cache).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(*source.requested.borrow(), vec![3]);
+    }
+
+    #[test]
+    fn prices_are_read_as_cents() {
+        let price: Price = serde_json::from_str(r#"{"currency": "EUR", "value": 19.99}"#).unwrap();
+        assert_eq!(price.cents, 1999);
+        assert_eq!(format_cents(price.cents, &price.currency), "19.99 EUR");
+        assert_eq!(format_decimal(-5), "-0.05");
+        assert_eq!(parse_cents(" 12.5 ").unwrap(), 1250);
+        assert!(parse_cents("twelve").is_err());
+        assert!(parse_cents("NaN").is_err());
+    }
+}